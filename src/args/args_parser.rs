@@ -1,3 +1,12 @@
+use crate::{
+    lint::sql_linter::{Level, Rule},
+    metrics::complexity_metrics::Metric,
+    sql::{
+        normalizer::{CasePolicy, PlaceholderStyle},
+        sample::SampleKind,
+    },
+    xbatis::def::ExplainFormat,
+};
 use getopts::{Matches, Options};
 use std::env;
 
@@ -26,6 +35,14 @@ pub enum DbType {
     Unknown,
     Oracle,
     MySQL,
+    Sqlite,
+    H2,
+    Dm,
+    Kingbase,
+    OpenGauss,
+    OceanBaseOracle,
+    OceanBaseMySql,
+    Db2,
 }
 
 impl DbType {
@@ -33,73 +50,173 @@ impl DbType {
         match name {
             "oracle" => DbType::Oracle,
             "mysql" => DbType::MySQL,
+            "sqlite" => DbType::Sqlite,
+            "h2" => DbType::H2,
+            "dm" => DbType::Dm,
+            "kingbase" => DbType::Kingbase,
+            "opengauss" | "gaussdb" => DbType::OpenGauss,
+            "oceanbase-oracle" => DbType::OceanBaseOracle,
+            "oceanbase-mysql" => DbType::OceanBaseMySql,
+            "db2" => DbType::Db2,
             _ => DbType::Unknown,
         }
     }
 }
 
+/// 子命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    /// 抽取 `sql` 到 `result.sql`
+    Extract,
+    /// 比对两个源码目录（或 `git` 版本）抽取出的 `sql`
+    Diff { base: String, head: String },
+    /// 连接数据库执行 `EXPLAIN`，收集每条语句的执行计划
+    ExplainRun { url: String },
+    /// 以 `-t` 为源方言，检查语句中目标方言不支持的写法
+    Compat { target: DbType },
+    /// 各语句读写的表、字段与关联条件
+    Inventory,
+    /// 读写指定表（及字段）的语句，含经由 `include` 引用的
+    Impact {
+        table: String,
+        column: Option<String>,
+    },
+    /// 按规则静态检查语句，存在 `error` 级别的问题时以非零值退出
+    Lint,
+    /// 未引用的片段、找不到的引用与重复的语句 `id`
+    Hygiene,
+    /// 规范化后相同或相近的语句，`threshold` 为相似度阈值
+    Duplicates { threshold: f64 },
+    /// 每条语句的复杂度指标
+    Metrics,
+}
+
+/// 报告格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl ReportFormat {
+    fn from(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(ReportFormat::Text),
+            "json" => Some(ReportFormat::Json),
+            "csv" => Some(ReportFormat::Csv),
+            _ => None,
+        }
+    }
+}
+
+/// 输入来源，多个目录与文件清单共用同一个 `include` 命名空间
 #[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sources {
+    /// 源码目录
+    pub dirs: Vec<String>,
+    /// 文件清单，`-` 表示从 `stdin` 读取
+    pub files_from: Option<String>,
+    /// 从 `stdin` 读取单个 `xml` 文档，结果输出到 `stdout`
+    pub stdin: bool,
+}
+
+impl Sources {
+    fn empty() -> Self {
+        Sources {
+            dirs: Vec::new(),
+            files_from: None,
+            stdin: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct Args {
+    pub command: Command,
     pub mode: XBatisMode,
     pub db_type: DbType,
-    pub src_dir: String,
+    pub sources: Sources,
     pub output_dir: String,
     pub gen_explain: bool,
+    pub explain_format: ExplainFormat,
     pub replace_num: i16,
     pub sql_limit: i16,
+    pub incremental: bool,
+    pub keep_comments: bool,
+    pub pretty: bool,
+    pub case: CasePolicy,
+    pub placeholder: PlaceholderStyle,
+    pub sample_values: bool,
+    pub prologue: bool,
+    /// 各类别样例值的覆盖
+    pub samples: Vec<(SampleKind, String)>,
+    /// 检查规则级别的覆盖
+    pub rules: Vec<(Rule, Level)>,
+    /// 指标报告的排序指标
+    pub sort: Option<Metric>,
+    /// 指标报告的阈值，达到任一阈值的语句才输出
+    pub minimums: Vec<(Metric, usize)>,
+    pub validate: bool,
+    pub watch: bool,
+    pub format: ReportFormat,
     pub fast_fail: bool,
     pub show_version: bool,
 }
 
 impl Args {
-    fn new(
-        mode: XBatisMode,
-        db_type: DbType,
-        src_dir: &str,
-        output_dir: &str,
-        gen_explain: bool,
-        replace_num: i16,
-        sql_limit: i16,
-    ) -> Self {
+    fn new(mode: XBatisMode, db_type: DbType, sources: Sources, output_dir: &str) -> Self {
         Args {
+            command: Command::Extract,
             mode,
             db_type,
-            src_dir: src_dir.to_owned(),
+            sources,
             output_dir: output_dir.to_owned(),
-            gen_explain,
-            replace_num,
-            sql_limit,
+            gen_explain: false,
+            explain_format: ExplainFormat::Text,
+            replace_num: REPLACE_NUM,
+            sql_limit: 0,
+            incremental: false,
+            keep_comments: false,
+            pretty: false,
+            case: CasePolicy::UpperKeywords,
+            placeholder: PlaceholderStyle::Dialect,
+            sample_values: false,
+            prologue: false,
+            samples: Vec::new(),
+            rules: Vec::new(),
+            sort: None,
+            minimums: Vec::new(),
+            validate: false,
+            watch: false,
+            format: ReportFormat::Text,
             fast_fail: false,
             show_version: false,
         }
     }
 
     fn fail() -> Self {
-        Args {
-            mode: XBatisMode::NotSupported,
-            db_type: DbType::Unknown,
-            src_dir: String::from(""),
-            output_dir: String::from(""),
-            gen_explain: false,
-            replace_num: 0,
-            sql_limit: 0,
-            fast_fail: true,
-            show_version: false,
-        }
+        let mut args = Args::new(
+            XBatisMode::NotSupported,
+            DbType::Unknown,
+            Sources::empty(),
+            "",
+        );
+        args.replace_num = 0;
+        args.fast_fail = true;
+        args
     }
 
     fn help() -> Self {
-        Args {
-            mode: XBatisMode::NotSupported,
-            db_type: DbType::Unknown,
-            src_dir: String::from(""),
-            output_dir: String::from(""),
-            gen_explain: false,
-            replace_num: 0,
-            sql_limit: 0,
-            fast_fail: false,
-            show_version: true,
-        }
+        let mut args = Args::new(
+            XBatisMode::NotSupported,
+            DbType::Unknown,
+            Sources::empty(),
+            "",
+        );
+        args.replace_num = 0;
+        args.show_version = true;
+        args
     }
 }
 
@@ -117,10 +234,27 @@ fn build_opts() -> Options {
     let mut opts = Options::new();
     opts.optflag("i", "ibatis", "try to parse iBATIS sqlmap files");
     opts.optflag("m", "mybatis", "try to parse MyBatis mapper files");
-    opts.optopt("t", "type", "db type", "DB");
-    opts.optopt("s", "src", "source directory", "SRC");
+    opts.optopt(
+        "t",
+        "type",
+        "db type: oracle/mysql/sqlite/h2/dm/kingbase/opengauss/oceanbase-oracle/oceanbase-mysql/db2",
+        "DB",
+    );
+    opts.optmulti("s", "src", "source directory, could be repeated", "SRC");
+    opts.optopt(
+        "f",
+        "files-from",
+        "read xml file list from file, `-` means stdin",
+        "LIST",
+    );
     opts.optopt("o", "output", "output directory", "OUTPUT");
     opts.optflag("e", "explain", "generate explain sql");
+    opts.optopt(
+        "",
+        "explain-format",
+        "explain format: text/json/analyze (MySQL)/plan-table (Oracle), default is text",
+        "FMT",
+    );
     opts.optopt(
         "n",
         "num",
@@ -128,6 +262,110 @@ fn build_opts() -> Options {
         "TIMES",
     );
     opts.optopt("l", "limit", "sql length limit", "LIMIT");
+    opts.optflag(
+        "",
+        "keep-comments",
+        "keep sql comments, optimizer hints are always kept",
+    );
+    opts.optflag(
+        "",
+        "pretty",
+        "format sql in multiple lines, one clause per line",
+    );
+    opts.optopt(
+        "",
+        "case",
+        "keyword case: upper-keywords/preserve/lower, default is upper-keywords",
+        "POLICY",
+    );
+    opts.optopt(
+        "",
+        "placeholder",
+        "placeholder style: dialect/?/:name/$n/@name, default is dialect (:? for Oracle, @1 for MySQL)",
+        "STYLE",
+    );
+    opts.optflag(
+        "",
+        "sample-values",
+        "replace placeholders with sample values of matching type, guessed from jdbcType/javaType/parameterType",
+    );
+    opts.optflag(
+        "",
+        "prologue",
+        "use variables as placeholders, and set them to sample values before each statement",
+    );
+    opts.optmulti(
+        "",
+        "sample",
+        "override a sample value, KIND is number/string/date/timestamp/boolean/default",
+        "KIND=VALUE",
+    );
+    opts.optflag(
+        "",
+        "validate",
+        "validate every statement with the dialect grammar, exit with 1 on syntax errors",
+    );
+    opts.optflag(
+        "",
+        "incremental",
+        "cache parsed result in output directory, only re-parse changed files",
+    );
+    opts.optflag(
+        "",
+        "watch",
+        "keep running, re-extract when xml files in source directories change",
+    );
+    opts.optopt(
+        "",
+        "base",
+        "diff mode, base directory or git revision",
+        "BASE",
+    );
+    opts.optopt(
+        "",
+        "head",
+        "diff mode, head directory or git revision",
+        "HEAD",
+    );
+    opts.optopt("", "target", "compat mode, target db type", "DB");
+    opts.optopt("", "table", "impact mode, table name", "TABLE");
+    opts.optopt("", "column", "impact mode, column name", "COLUMN");
+    opts.optopt(
+        "",
+        "threshold",
+        "duplicates mode, similarity threshold in (0, 1], default is 0.9",
+        "T",
+    );
+    opts.optmulti(
+        "",
+        "rule",
+        "lint mode, set the level of a rule, LEVEL is off/warn/error",
+        "RULE=LEVEL",
+    );
+    opts.optopt(
+        "",
+        "sort",
+        "metrics mode, sort statements by a metric in descending order",
+        "METRIC",
+    );
+    opts.optmulti(
+        "",
+        "min",
+        "metrics mode, only report statements reaching any of the thresholds",
+        "METRIC=N",
+    );
+    opts.optopt(
+        "",
+        "url",
+        "explain run, database url: mysql://..., postgres://..., sqlite://PATH",
+        "URL",
+    );
+    opts.optopt(
+        "",
+        "format",
+        "report format: text/json, csv for inventory, impact, duplicates and metrics, default is text",
+        "FMT",
+    );
     opts.optflag("v", "version", "show version information");
     opts.optflag("h", "help", "print this help menu");
     opts
@@ -139,9 +377,72 @@ fn actual_check_args(opts: Options, matches: Matches) -> (Args, Options) {
     let mode_ibatis = matches.opt_present("i");
     let mode_mybatis = matches.opt_present("m");
     let o_db_type = matches.opt_str("t");
-    let src_dir = matches.opt_str("s");
+    let src_dirs = matches.opt_strs("s");
+    let files_from = matches.opt_str("f");
+    let stdin = matches.free.iter().any(|f| f == "-");
     let output_dir = matches.opt_str("o");
     let gen_explain = matches.opt_present("e");
+    let explain_format = matches
+        .opt_str("explain-format")
+        .unwrap_or(String::from("text"));
+    let incremental = matches.opt_present("incremental");
+    let watch = matches.opt_present("watch");
+    let keep_comments = matches.opt_present("keep-comments");
+    let pretty = matches.opt_present("pretty");
+    let case = matches
+        .opt_str("case")
+        .unwrap_or(String::from("upper-keywords"));
+    let placeholder = matches
+        .opt_str("placeholder")
+        .unwrap_or(String::from("dialect"));
+    let sample_values = matches.opt_present("sample-values");
+    let prologue = matches.opt_present("prologue");
+    let samples = match parse_samples(&matches.opt_strs("sample")) {
+        Ok(samples) => samples,
+        Err(f) => fail!(f, opts),
+    };
+    let rules = match parse_rules(&matches.opt_strs("rule")) {
+        Ok(rules) => rules,
+        Err(f) => fail!(f, opts),
+    };
+    let sort = match matches
+        .opt_str("sort")
+        .map(|name| {
+            Metric::from(name.trim().to_ascii_lowercase().as_str())
+                .ok_or(format!("unknown metric: {name}"))
+        })
+        .transpose()
+    {
+        Ok(sort) => sort,
+        Err(f) => fail!(f, opts),
+    };
+    let minimums = match parse_minimums(&matches.opt_strs("min")) {
+        Ok(minimums) => minimums,
+        Err(f) => fail!(f, opts),
+    };
+    let validate = matches.opt_present("validate");
+    let format = matches.opt_str("format").unwrap_or(String::from("text"));
+    let command = match parse_command(&matches) {
+        Ok(command) => command,
+        Err(f) => fail!(f, opts),
+    };
+    let extract = command == Command::Extract;
+    let explain_run = matches!(command, Command::ExplainRun { .. });
+    let inventory = command == Command::Inventory;
+    let impact = matches!(command, Command::Impact { .. });
+    let lint = command == Command::Lint;
+    let hygiene = command == Command::Hygiene;
+    let duplicates = matches!(command, Command::Duplicates { .. });
+    let metrics = command == Command::Metrics;
+    let needs_sources = extract
+        || explain_run
+        || inventory
+        || impact
+        || lint
+        || hygiene
+        || duplicates
+        || metrics
+        || matches!(command, Command::Compat { .. });
     let num = matches
         .opt_str("n")
         .unwrap_or(String::from(REPLACE_NUM_STR))
@@ -160,38 +461,251 @@ fn actual_check_args(opts: Options, matches: Matches) -> (Args, Options) {
         fail!("must choose in iBATIS mode or MyBatis mode", opts);
     } else if o_db_type.is_none() {
         fail!("must define the db type", opts);
-    } else if src_dir.is_none() {
-        fail!("must define the source directory", opts);
-    } else if output_dir.is_none() {
+    } else if stdin && (!src_dirs.is_empty() || files_from.is_some()) {
+        fail!("reading from stdin can not be mixed with -s or -f", opts);
+    } else if !extract && needs_sources && stdin {
+        fail!("subcommands except diff only work with -s or -f", opts);
+    } else if needs_sources && !stdin && src_dirs.is_empty() && files_from.is_none() {
+        fail!("must define the source directory or the file list", opts);
+    } else if watch && (!extract || stdin) {
+        fail!("watch mode only works with -s or -f", opts);
+    } else if validate && (!extract || stdin || watch) {
+        fail!("validation only works with -s or -f", opts);
+    } else if matches.opt_present("explain-format") && !gen_explain {
+        fail!("--explain-format only works with -e", opts);
+    } else if prologue && (sample_values || matches.opt_present("placeholder")) {
+        fail!(
+            "--prologue can not be mixed with --sample-values or --placeholder",
+            opts
+        );
+    } else if explain_run && (prologue || gen_explain) {
+        fail!("explain run can not be mixed with --prologue or -e", opts);
+    } else if !samples.is_empty() && !sample_values && !prologue && !explain_run {
+        fail!(
+            "--sample only works with --sample-values or --prologue",
+            opts
+        );
+    } else if !rules.is_empty() && !lint {
+        fail!("--rule only works with lint", opts);
+    } else if matches.opt_present("threshold") && !duplicates {
+        fail!("--threshold only works with duplicates", opts);
+    } else if (sort.is_some() || !minimums.is_empty()) && !metrics {
+        fail!("--sort and --min only work with metrics", opts);
+    } else if extract && !stdin && output_dir.is_none() {
         fail!("must define the output directory", opts);
     }
+    let format = match ReportFormat::from(format.to_ascii_lowercase().as_str()) {
+        Some(format) => format,
+        None => fail!("must choose report format in text, json or csv", opts),
+    };
+    if format == ReportFormat::Csv && !inventory && !impact && !duplicates && !metrics {
+        fail!(
+            "csv format only works with inventory, impact, duplicates and metrics",
+            opts
+        );
+    }
+    let case = match CasePolicy::from(case.to_ascii_lowercase().as_str()) {
+        Some(case) => case,
+        None => fail!(
+            "must choose keyword case in upper-keywords, preserve or lower",
+            opts
+        ),
+    };
+    let placeholder = match PlaceholderStyle::from(placeholder.to_ascii_lowercase().as_str()) {
+        Some(placeholder) => placeholder,
+        None => fail!(
+            "must choose placeholder style in dialect, ?, :name, $n or @name",
+            opts
+        ),
+    };
+    let explain_format = match ExplainFormat::from(explain_format.to_ascii_lowercase().as_str()) {
+        Some(explain_format) => explain_format,
+        None => fail!(
+            "must choose explain format in text, json, analyze or plan-table",
+            opts
+        ),
+    };
     let db_type = DbType::from(
         o_db_type
             .unwrap_or(String::from(""))
             .to_ascii_lowercase()
             .as_str(),
     );
+    match (db_type, explain_format) {
+        (DbType::Unknown, _) | (_, ExplainFormat::Text) => {}
+        (
+            DbType::MySQL | DbType::Kingbase | DbType::OpenGauss,
+            ExplainFormat::Json | ExplainFormat::Analyze,
+        ) => {}
+        (DbType::OceanBaseMySql, ExplainFormat::Json) => {}
+        (DbType::H2, ExplainFormat::Analyze) => {}
+        (DbType::Oracle, ExplainFormat::PlanTable) => {}
+        (_, ExplainFormat::Json) => fail!(
+            "explain format json only works with MySQL, Kingbase, openGauss or OceanBase (MySQL mode)",
+            opts
+        ),
+        (_, ExplainFormat::Analyze) => fail!(
+            "explain format analyze only works with MySQL, H2, Kingbase or openGauss",
+            opts
+        ),
+        (_, ExplainFormat::PlanTable) => {
+            fail!("explain format plan-table only works with Oracle", opts)
+        }
+    }
+    if prologue
+        && !matches!(
+            db_type,
+            DbType::Unknown | DbType::Oracle | DbType::MySQL | DbType::H2 | DbType::OceanBaseMySql
+        )
+    {
+        fail!(
+            "--prologue only works with Oracle, MySQL, H2 or OceanBase (MySQL mode)",
+            opts
+        );
+    }
     match db_type {
-        DbType::Unknown => fail!("must choose db type in oracle or mysql", opts),
+        DbType::Unknown => fail!(
+            "must choose db type in oracle, mysql, sqlite, h2, dm, kingbase, opengauss, oceanbase-oracle, oceanbase-mysql or db2",
+            opts
+        ),
         _ => {
             let mode = if mode_ibatis {
                 XBatisMode::IBatis
             } else {
                 XBatisMode::MyBatis
             };
-            (
-                Args::new(
-                    mode,
-                    db_type,
-                    &src_dir.unwrap_or(String::from("")),
-                    &output_dir.unwrap_or(String::from("")),
-                    gen_explain,
-                    num.parse::<i16>().unwrap_or(REPLACE_NUM),
-                    limit.parse::<i16>().unwrap_or(REPLACE_NUM),
-                ),
-                opts,
-            )
+            let mut args = Args::new(
+                mode,
+                db_type,
+                Sources {
+                    dirs: src_dirs,
+                    files_from,
+                    stdin,
+                },
+                &output_dir.unwrap_or(String::from("")),
+            );
+            args.gen_explain = gen_explain;
+            args.explain_format = explain_format;
+            args.replace_num = num.parse::<i16>().unwrap_or(REPLACE_NUM);
+            args.sql_limit = limit.parse::<i16>().unwrap_or(REPLACE_NUM);
+            args.incremental = incremental;
+            args.watch = watch;
+            args.keep_comments = keep_comments;
+            args.pretty = pretty;
+            args.case = case;
+            args.placeholder = placeholder;
+            // 执行计划需要可执行的语句，占位符总是换成样例值
+            args.sample_values = sample_values || explain_run;
+            args.prologue = prologue;
+            args.samples = samples;
+            args.rules = rules;
+            args.sort = sort;
+            args.minimums = minimums;
+            args.validate = validate;
+            args.format = format;
+            args.command = command;
+            (args, opts)
+        }
+    }
+}
+
+/// 解析 `KIND=VALUE` 形式的样例值
+fn parse_samples(values: &[String]) -> Result<Vec<(SampleKind, String)>, String> {
+    values
+        .iter()
+        .map(|v| {
+            let (kind, value) = v
+                .split_once('=')
+                .ok_or(format!("sample value must be KIND=VALUE: {v}"))?;
+            match SampleKind::from(kind.trim().to_ascii_lowercase().as_str()) {
+                Some(kind) => Ok((kind, value.to_string())),
+                None => Err(format!("unknown sample kind: {kind}")),
+            }
+        })
+        .collect()
+}
+
+/// 解析 `RULE=LEVEL` 形式的规则级别
+fn parse_rules(values: &[String]) -> Result<Vec<(Rule, Level)>, String> {
+    values
+        .iter()
+        .map(|v| {
+            let (rule, level) = v
+                .split_once('=')
+                .ok_or(format!("rule level must be RULE=LEVEL: {v}"))?;
+            let rule = Rule::from(rule.trim().to_ascii_lowercase().as_str())
+                .ok_or(format!("unknown lint rule: {rule}"))?;
+            match Level::from(level.trim().to_ascii_lowercase().as_str()) {
+                Some(level) => Ok((rule, level)),
+                None => Err(format!("unknown rule level: {level}")),
+            }
+        })
+        .collect()
+}
+
+/// 解析 `METRIC=N` 形式的指标阈值
+fn parse_minimums(values: &[String]) -> Result<Vec<(Metric, usize)>, String> {
+    values
+        .iter()
+        .map(|v| {
+            let (metric, min) = v
+                .split_once('=')
+                .ok_or(format!("metric threshold must be METRIC=N: {v}"))?;
+            let metric = Metric::from(metric.trim().to_ascii_lowercase().as_str())
+                .ok_or(format!("unknown metric: {metric}"))?;
+            match min.trim().parse::<usize>() {
+                Ok(min) => Ok((metric, min)),
+                Err(_) => Err(format!("metric threshold must be a number: {min}")),
+            }
+        })
+        .collect()
+}
+
+/// 自由参数中第一个非 `-` 的词为子命令
+fn parse_command(matches: &Matches) -> Result<Command, String> {
+    let words: Vec<&String> = matches.free.iter().filter(|f| *f != "-").collect();
+    match words.first().map(|w| w.as_str()) {
+        None => Ok(Command::Extract),
+        Some("diff") => match (matches.opt_str("base"), matches.opt_str("head")) {
+            (Some(base), Some(head)) => Ok(Command::Diff { base, head }),
+            _ => Err(String::from(
+                "must define both --base and --head in diff mode",
+            )),
+        },
+        Some("explain") => match (words.get(1).map(|w| w.as_str()), matches.opt_str("url")) {
+            (Some("run"), Some(url)) => Ok(Command::ExplainRun { url }),
+            (Some("run"), None) => Err(String::from("must define --url in explain run")),
+            _ => Err(String::from(
+                "unknown command: explain, do you mean explain run?",
+            )),
+        },
+        Some("compat") => match matches
+            .opt_str("target")
+            .map(|t| DbType::from(&t.to_ascii_lowercase()))
+        {
+            Some(DbType::Unknown) => Err(String::from("unknown target db type")),
+            Some(target) => Ok(Command::Compat { target }),
+            None => Err(String::from("must define --target in compat mode")),
+        },
+        Some("inventory") => Ok(Command::Inventory),
+        Some("impact") => match matches.opt_str("table") {
+            Some(table) => Ok(Command::Impact {
+                table,
+                column: matches.opt_str("column"),
+            }),
+            None => Err(String::from("must define --table in impact mode")),
+        },
+        Some("lint") => Ok(Command::Lint),
+        Some("hygiene") => Ok(Command::Hygiene),
+        Some("duplicates") => {
+            let threshold = matches.opt_str("threshold").unwrap_or(String::from("0.9"));
+            match threshold.trim().parse::<f64>() {
+                Ok(t) if t > 0.0 && t <= 1.0 => Ok(Command::Duplicates { threshold: t }),
+                _ => Err(format!("threshold must be a number in (0, 1]: {threshold}")),
+            }
         }
+        Some("metrics") => Ok(Command::Metrics),
+        Some(other) => Err(format!("unknown command: {other}")),
     }
 }
 
@@ -199,7 +713,19 @@ fn actual_check_args(opts: Options, matches: Matches) -> (Args, Options) {
 pub fn print_usage(options: &Options) {
     print!(
         "{}",
-        options.usage("Usage: xbatis2sql [-i|-m] -t [Oracle/MySQL] -s ... -o ... [-e] [-n 10] [-l 1000]")
+        options.usage(concat!(
+            "Usage: xbatis2sql [-i|-m] -t DB -s ... [-s ...] [-f LIST] -o ... [-e [--explain-format FMT]] [-n 10] [-l 1000] [--case POLICY] [--placeholder STYLE] [--sample-values|--prologue [--sample KIND=VALUE]] [--pretty] [--incremental] [--watch] [--validate]\n",
+            "       xbatis2sql [-i|-m] -t DB [-e] [-n 10] [-l 1000] - < mapper.xml\n",
+            "       xbatis2sql diff [-i|-m] -t DB --base ... --head ... [--format text/json]\n",
+            "       xbatis2sql explain run [-i|-m] -t DB -s ... [-f LIST] [-o ...] --url URL [--sample KIND=VALUE] [--format text/json]\n",
+            "       xbatis2sql compat [-i|-m] -t DB -s ... [-f LIST] --target DB [--format text/json]\n",
+            "       xbatis2sql inventory [-i|-m] -t DB -s ... [-f LIST] [--format text/json/csv]\n",
+            "       xbatis2sql impact [-i|-m] -t DB -s ... [-f LIST] --table TABLE [--column COLUMN] [--format text/json/csv]\n",
+            "       xbatis2sql lint [-i|-m] -t DB -s ... [-f LIST] [--rule RULE=LEVEL] [--format text/json]\n",
+            "       xbatis2sql hygiene [-i|-m] -t DB -s ... [-f LIST] [--format text/json]\n",
+            "       xbatis2sql duplicates [-i|-m] -t DB -s ... [-f LIST] [--threshold 0.9] [--format text/json/csv]\n",
+            "       xbatis2sql metrics [-i|-m] -t DB -s ... [-f LIST] [--sort METRIC] [--min METRIC=N] [--format text/json/csv]"
+        ))
     );
 }
 
@@ -211,3 +737,133 @@ pub fn print_version() {
     println!("version: {}", env!("CARGO_PKG_VERSION"));
     println!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        let opts = build_opts();
+        let matches = opts.parse(args).unwrap();
+        actual_check_args(opts, matches).0
+    }
+
+    #[test]
+    fn multiple_sources_and_file_list() {
+        let args = parse(&[
+            "-m", "-t", "mysql", "-s", "a", "-s", "b", "-f", "list.txt", "-o", "out",
+        ]);
+        assert!(!args.fast_fail);
+        assert_eq!(args.sources.dirs, ["a", "b"]);
+        assert_eq!(args.sources.files_from.as_deref(), Some("list.txt"));
+        let args = parse(&["-m", "-t", "mysql", "-f", "-", "-o", "out"]);
+        assert!(!args.fast_fail);
+        assert!(args.sources.dirs.is_empty());
+        assert!(parse(&["-m", "-t", "mysql", "-o", "out"]).fast_fail);
+    }
+
+    #[test]
+    fn stdin_needs_no_output_and_excludes_sources() {
+        let args = parse(&["-m", "-t", "mysql", "-"]);
+        assert!(!args.fast_fail);
+        assert!(args.sources.stdin);
+        assert!(parse(&["-m", "-t", "mysql", "-s", "a", "-"]).fast_fail);
+        assert!(parse(&["-m", "-t", "mysql", "-f", "list.txt", "-"]).fast_fail);
+    }
+
+    #[test]
+    fn diff_needs_base_and_head() {
+        let args = parse(&["diff", "-m", "-t", "mysql", "--base", "a", "--head", "b"]);
+        assert!(!args.fast_fail);
+        assert_eq!(
+            args.command,
+            Command::Diff {
+                base: String::from("a"),
+                head: String::from("b")
+            }
+        );
+        assert!(parse(&["diff", "-m", "-t", "mysql", "--base", "a"]).fast_fail);
+    }
+
+    #[test]
+    fn watch_needs_sources() {
+        let args = parse(&["-m", "-t", "mysql", "-s", "a", "-o", "out", "--watch"]);
+        assert!(!args.fast_fail);
+        assert!(args.watch);
+        assert!(parse(&["-m", "-t", "mysql", "--watch", "-"]).fast_fail);
+    }
+
+    #[test]
+    fn case_policy_is_checked() {
+        let args = parse(&["-m", "-t", "mysql", "-"]);
+        assert_eq!(args.case, CasePolicy::UpperKeywords);
+        let args = parse(&["-m", "-t", "mysql", "--case", "LOWER", "-"]);
+        assert!(!args.fast_fail);
+        assert_eq!(args.case, CasePolicy::Lower);
+        assert!(parse(&["-m", "-t", "mysql", "--case", "upper", "-"]).fast_fail);
+    }
+
+    #[test]
+    fn samples_need_sample_values() {
+        let args = parse(&[
+            "-m",
+            "-t",
+            "mysql",
+            "--sample-values",
+            "--sample",
+            "NUMBER=42",
+            "--sample",
+            "string='abc'",
+            "-",
+        ]);
+        assert!(!args.fast_fail);
+        assert!(args.sample_values);
+        assert_eq!(
+            args.samples,
+            [
+                (SampleKind::Number, String::from("42")),
+                (SampleKind::String, String::from("'abc'"))
+            ]
+        );
+        assert!(parse(&["-m", "-t", "mysql", "--sample", "number=42", "-"]).fast_fail);
+        let sample = ["-m", "-t", "mysql", "--sample-values", "--sample"];
+        assert!(parse(&[&sample[..], &["number", "-"]].concat()).fast_fail);
+        assert!(parse(&[&sample[..], &["money=1", "-"]].concat()).fast_fail);
+    }
+
+    #[test]
+    fn duplicates_threshold_keeps_its_precision() {
+        let duplicates = ["duplicates", "-m", "-t", "mysql", "-s", "a"];
+        let args = parse(&duplicates);
+        assert!(!args.fast_fail);
+        assert_eq!(args.command, Command::Duplicates { threshold: 0.9 });
+        let args = parse(&[&duplicates[..], &["--threshold", "0.925"]].concat());
+        assert_eq!(args.command, Command::Duplicates { threshold: 0.925 });
+        assert!(parse(&[&duplicates[..], &["--threshold", "0"]].concat()).fast_fail);
+        assert!(parse(&[&duplicates[..], &["--threshold", "1.5"]].concat()).fast_fail);
+    }
+
+    #[test]
+    fn metrics_sort_and_minimums() {
+        let metrics = ["metrics", "-m", "-t", "mysql", "-s", "a"];
+        let args = parse(&[&metrics[..], &["--sort", "Joins", "--min", "joins=4"]].concat());
+        assert!(!args.fast_fail);
+        assert_eq!(args.sort, Some(Metric::Joins));
+        let args = parse(&[&metrics[..], &["--min", "joins=4", "--min", "params=2"]].concat());
+        assert_eq!(args.minimums, [(Metric::Joins, 4), (Metric::Params, 2)]);
+        assert!(parse(&[&metrics[..], &["--sort", "width"]].concat()).fast_fail);
+        assert!(parse(&[&metrics[..], &["--min", "joins"]].concat()).fast_fail);
+        assert!(parse(&[&metrics[..], &["--min", "joins=x"]].concat()).fast_fail);
+        let duplicates = [
+            "duplicates",
+            "-m",
+            "-t",
+            "mysql",
+            "-s",
+            "a",
+            "--sort",
+            "joins",
+        ];
+        assert!(parse(&duplicates).fast_fail);
+    }
+}
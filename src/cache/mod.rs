@@ -0,0 +1,2 @@
+/// 增量解析缓存
+pub mod parsed_cache;
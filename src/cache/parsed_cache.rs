@@ -0,0 +1,200 @@
+use crate::xbatis::{
+    def::{ParsedXml, PARSED_VERSION},
+    xbatis_parser::Parser,
+};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+/// 缓存文件名，位于输出目录下，清空输出目录即全部重新解析
+const CACHE_FILE: &str = "xbatis2sql.cache.json";
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    /// 解析结果的版本，见 `PARSED_VERSION`
+    version: u32,
+    /// 文件名 -> 缓存项
+    entries: HashMap<String, CacheEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    /// 文件内容与解析选项的摘要
+    hash: String,
+    /// 解析结果，`None` 表示文件不是当前模式下的 `xml`
+    parsed: Option<ParsedXml>,
+}
+
+/// 以文件内容摘要为键的解析缓存
+pub struct ParsedCache {
+    path: PathBuf,
+    /// 解析选项指纹，参与摘要计算
+    options: String,
+    old: HashMap<String, CacheEntry>,
+    new: Mutex<CacheFile>,
+    hits: Mutex<(usize, usize)>,
+}
+
+impl ParsedCache {
+    /// 读取输出目录下的缓存，不存在或版本不符时视为空缓存
+    pub fn load(output_dir: &String, options: String) -> Self {
+        let path = PathBuf::from(format!("{output_dir}/{CACHE_FILE}"));
+        let old = File::open(&path)
+            .ok()
+            .and_then(|f| serde_json::from_reader::<_, CacheFile>(BufReader::new(f)).ok())
+            .filter(|c| c.version == PARSED_VERSION)
+            .map(|c| c.entries)
+            .unwrap_or_default();
+        info!("load {} cached entries from {path:?}", old.len());
+        ParsedCache {
+            path,
+            options,
+            old,
+            new: Mutex::new(CacheFile {
+                version: PARSED_VERSION,
+                entries: HashMap::new(),
+            }),
+            hits: Mutex::new((0, 0)),
+        }
+    }
+
+    /// 解析文件。内容未变时直接使用缓存的解析结果，`include` 依旧每次重新替换
+    pub fn parse(
+        &self,
+        parser: &dyn Parser,
+        file: &String,
+        global_inc_map: &Mutex<HashMap<String, String>>,
+    ) -> Option<Vec<String>> {
+        let content = match fs::read(file) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("read file [{file}] failed: {e}");
+                return None;
+            }
+        };
+        let hash = self.digest(&content);
+        let parsed = match self.old.get(file).filter(|e| e.hash == hash) {
+            Some(entry) => {
+                self.count(true);
+                entry.parsed.clone()
+            }
+            None => {
+                self.count(false);
+                if parser.detect_match(file) {
+                    info!("try to parse [{file}]");
+                    Some(parser.parse_xml(file, &mut content.as_slice()))
+                } else {
+                    None
+                }
+            }
+        };
+        let mut sql_store = None;
+        if let Some(p) = &parsed {
+            if let Ok(mut map) = global_inc_map.lock() {
+                let mut sqls = Vec::new();
+                parser.fill_parsed(file, p, &mut sqls, &mut map);
+                sql_store = Some(sqls);
+            }
+        }
+        if let Ok(mut new) = self.new.lock() {
+            new.entries
+                .insert(file.clone(), CacheEntry { hash, parsed });
+        }
+        sql_store
+    }
+
+    /// 保存本次运行涉及的文件，已删除的文件随之淘汰
+    pub fn save(&self) {
+        let (hits, misses) = *self.hits.lock().unwrap();
+        info!("cache hits: {hits}, re-parsed: {misses}");
+        let rs = File::create(&self.path).map(BufWriter::new).map(|w| {
+            let new = self.new.lock().unwrap();
+            serde_json::to_writer(w, &*new)
+        });
+        match rs {
+            Ok(Ok(())) => info!("save cache to {:?}", self.path),
+            Ok(Err(e)) => warn!("save cache to {:?} failed: {e}", self.path),
+            Err(e) => warn!("save cache to {:?} failed: {e}", self.path),
+        }
+    }
+
+    fn digest(&self, content: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.options.as_bytes());
+        hasher.update(content);
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn count(&self, hit: bool) {
+        if let Ok(mut hits) = self.hits.lock() {
+            if hit {
+                hits.0 += 1;
+            } else {
+                hits.1 += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xbatis::{def::DialectType, mybatis_parser};
+    use std::process;
+
+    fn temp_dir(name: &str) -> String {
+        let dir = std::env::temp_dir().join(format!("xbatis2sql-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.to_string_lossy().to_string()
+    }
+
+    /// 以给定选项加载缓存并解析一次文件，返回命中与重新解析的次数
+    fn run(dir: &String, file: &String, options: &str) -> (usize, usize) {
+        let parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        let cache = ParsedCache::load(dir, String::from(options));
+        assert!(cache
+            .parse(&parser, file, &Mutex::new(HashMap::new()))
+            .is_some());
+        cache.save();
+        let hits = *cache.hits.lock().unwrap();
+        hits
+    }
+
+    #[test]
+    fn hits_misses_and_invalidation() {
+        let dir = temp_dir("cache");
+        let file = format!("{dir}/m.xml");
+        let mapper = |sql: &str| {
+            format!(
+                "<!DOCTYPE mapper PUBLIC \"-//mybatis.org//DTD Mapper 3.0//EN\" \"\">\n<mapper namespace=\"m\"><select id=\"a\">{sql}</select></mapper>"
+            )
+        };
+        fs::write(&file, mapper("SELECT 1")).unwrap();
+        assert_eq!(run(&dir, &file, "o"), (0, 1));
+        assert_eq!(run(&dir, &file, "o"), (1, 0));
+        // 内容或选项变化时重新解析
+        fs::write(&file, mapper("SELECT 2")).unwrap();
+        assert_eq!(run(&dir, &file, "o"), (0, 1));
+        assert_eq!(run(&dir, &file, "p"), (0, 1));
+        // 解析结果版本不符时整个缓存作废
+        let path = format!("{dir}/{CACHE_FILE}");
+        let mut cached: CacheFile =
+            serde_json::from_reader(BufReader::new(File::open(&path).unwrap())).unwrap();
+        cached.version = PARSED_VERSION + 1;
+        serde_json::to_writer(File::create(&path).unwrap(), &cached).unwrap();
+        assert_eq!(run(&dir, &file, "p"), (0, 1));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,302 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    sql::lexer::{self, TemplateSyntax, Token, TokenKind},
+    xbatis::def::DialectType,
+};
+use log::warn;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 与方言相关的写法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Rownum,
+    Nvl,
+    Decode,
+    ConnectBy,
+    /// `(+)` 外连接
+    OuterJoinPlus,
+    Sysdate,
+    /// 序列的 `.NEXTVAL`/`.CURRVAL`
+    Sequence,
+    Merge,
+    Minus,
+    /// 优化器提示 `/*+ ... */`，`MySQL` 5.7 起同样支持
+    Hint,
+    /// `||` 拼接，`MySQL` 默认视为 `OR`
+    PipeConcat,
+    Limit,
+    Ifnull,
+    /// `` `name` `` 加引号的标识符
+    Backtick,
+    OnDuplicateKey,
+    GroupConcat,
+}
+
+impl Feature {
+    pub fn name(&self) -> &str {
+        match self {
+            Feature::Rownum => "ROWNUM",
+            Feature::Nvl => "NVL",
+            Feature::Decode => "DECODE",
+            Feature::ConnectBy => "CONNECT BY",
+            Feature::OuterJoinPlus => "(+)",
+            Feature::Sysdate => "SYSDATE",
+            Feature::Sequence => ".NEXTVAL",
+            Feature::Merge => "MERGE",
+            Feature::Minus => "MINUS",
+            Feature::Hint => "hint",
+            Feature::PipeConcat => "||",
+            Feature::Limit => "LIMIT",
+            Feature::Ifnull => "IFNULL",
+            Feature::Backtick => "`identifier`",
+            Feature::OnDuplicateKey => "ON DUPLICATE KEY",
+            Feature::GroupConcat => "GROUP_CONCAT",
+        }
+    }
+
+    /// 目标方言是否支持
+    pub fn supported_by(&self, dialect: &DialectType) -> bool {
+        let oracle_like = matches!(
+            dialect,
+            DialectType::Oracle | DialectType::Dm | DialectType::OceanBaseOracle
+        );
+        let mysql_like = matches!(dialect, DialectType::MySQL | DialectType::OceanBaseMySql);
+        match self {
+            Feature::Rownum
+            | Feature::Nvl
+            | Feature::Decode
+            | Feature::ConnectBy
+            | Feature::OuterJoinPlus
+            | Feature::Sysdate
+            | Feature::Minus => oracle_like,
+            Feature::Hint => oracle_like || mysql_like,
+            Feature::Sequence => oracle_like || matches!(dialect, DialectType::Db2),
+            Feature::Merge => !mysql_like && !matches!(dialect, DialectType::Sqlite),
+            Feature::PipeConcat => !mysql_like,
+            Feature::Limit => !oracle_like && !matches!(dialect, DialectType::Db2),
+            Feature::Ifnull => {
+                mysql_like || matches!(dialect, DialectType::Sqlite | DialectType::H2)
+            }
+            Feature::Backtick => mysql_like || matches!(dialect, DialectType::Sqlite),
+            Feature::OnDuplicateKey | Feature::GroupConcat => mysql_like,
+        }
+    }
+}
+
+/// 单条语句中目标方言不支持的写法
+#[derive(Serialize)]
+pub struct Finding {
+    pub file: String,
+    pub key: String,
+    pub sql: String,
+    pub features: Vec<String>,
+}
+
+/// 兼容性报告，计数为出现次数
+#[derive(Serialize, Default)]
+pub struct CompatReport {
+    pub total: usize,
+    pub incompatible: usize,
+    /// 各写法的出现次数
+    pub by_feature: BTreeMap<String, usize>,
+    /// 各文件中各写法的出现次数
+    pub by_file: BTreeMap<String, BTreeMap<String, usize>>,
+    pub findings: Vec<Finding>,
+}
+
+/// 按源方言切分每条语句，找出目标方言不支持的写法
+pub fn check(sqls: &[ExtractedSql], source: &DialectType, target: &DialectType) -> CompatReport {
+    let mut report = CompatReport {
+        total: sqls.len(),
+        ..Default::default()
+    };
+    for stat in sqls {
+        let features: Vec<Feature> = detect(&stat.sql, source)
+            .into_iter()
+            .filter(|f| !f.supported_by(target))
+            .collect();
+        if features.is_empty() {
+            continue;
+        }
+        let file_counts = report.by_file.entry(stat.file.clone()).or_default();
+        for feature in features.iter() {
+            *report
+                .by_feature
+                .entry(feature.name().to_string())
+                .or_default() += 1;
+            *file_counts.entry(feature.name().to_string()).or_default() += 1;
+        }
+        let mut names: Vec<String> = Vec::new();
+        for feature in features.iter() {
+            if !names.iter().any(|n| n == feature.name()) {
+                names.push(feature.name().to_string());
+            }
+        }
+        report.incompatible += 1;
+        report.findings.push(Finding {
+            file: stat.file.clone(),
+            key: stat.key(),
+            sql: stat.sql.clone(),
+            features: names,
+        });
+    }
+    report
+}
+
+/// 语句中出现的写法，按出现顺序，重复出现的逐个列出
+fn detect(sql: &str, dialect: &DialectType) -> Vec<Feature> {
+    let tokens = lexer::tokenize(sql, dialect, TemplateSyntax::MyBatis);
+    let mut features = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let next = tokens.get(i + 1);
+        let feature = match token.kind {
+            TokenKind::Hint => Some(Feature::Hint),
+            TokenKind::QuotedIdent if token.text.starts_with('`') => Some(Feature::Backtick),
+            TokenKind::Word => detect_word(token, &tokens, i),
+            TokenKind::Symbol if token.is_symbol("(") => {
+                let closed = tokens.get(i + 2).is_some_and(|t| t.is_symbol(")"));
+                (next.is_some_and(|t| t.is_symbol("+")) && closed).then_some(Feature::OuterJoinPlus)
+            }
+            TokenKind::Symbol if token.is_symbol("|") => next
+                .filter(|t| t.is_symbol("|") && !t.space_before)
+                .map(|_| Feature::PipeConcat),
+            TokenKind::Symbol if token.is_symbol(".") => next
+                .filter(|t| t.is_word("NEXTVAL") || t.is_word("CURRVAL"))
+                .map(|_| Feature::Sequence),
+            _ => None,
+        };
+        features.extend(feature);
+    }
+    features
+}
+
+fn detect_word(token: &Token, tokens: &[Token], i: usize) -> Option<Feature> {
+    let next = tokens.get(i + 1);
+    let call = next.is_some_and(|t| t.is_symbol("("));
+    let after_dot = i > 0 && tokens[i - 1].is_symbol(".");
+    match token.text.to_ascii_uppercase().as_str() {
+        _ if after_dot => None,
+        "ROWNUM" => Some(Feature::Rownum),
+        "NVL" | "NVL2" if call => Some(Feature::Nvl),
+        "DECODE" if call => Some(Feature::Decode),
+        "CONNECT" if next.is_some_and(|t| t.is_word("BY")) => Some(Feature::ConnectBy),
+        "SYSDATE" | "SYSTIMESTAMP" => Some(Feature::Sysdate),
+        "MERGE" if next.is_some_and(|t| t.is_word("INTO")) => Some(Feature::Merge),
+        "MINUS" => Some(Feature::Minus),
+        "LIMIT" => Some(Feature::Limit),
+        "IFNULL" if call => Some(Feature::Ifnull),
+        "GROUP_CONCAT" if call => Some(Feature::GroupConcat),
+        "DUPLICATE" if i > 0 && tokens[i - 1].is_word("ON") => Some(Feature::OnDuplicateKey),
+        _ => None,
+    }
+}
+
+/// 文本格式输出
+pub fn print_text(report: &CompatReport) {
+    for finding in report.findings.iter() {
+        println!(
+            "{}: {} [{}]",
+            finding.file,
+            finding.key,
+            finding.features.join(", ")
+        );
+    }
+    if !report.by_file.is_empty() {
+        println!();
+        for (file, counts) in report.by_file.iter() {
+            println!("{file}: {}", join_counts(counts));
+        }
+        println!();
+        println!("total: {}", join_counts(&report.by_feature));
+    }
+    println!(
+        "checked: {}, incompatible: {}",
+        report.total, report.incompatible
+    );
+}
+
+fn join_counts(counts: &BTreeMap<String, usize>) -> String {
+    counts
+        .iter()
+        .map(|(name, count)| format!("{name} x{count}"))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &CompatReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize compat report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(id: &str, sql: &str) -> ExtractedSql {
+        ExtractedSql {
+            file: String::from("order.xml"),
+            namespace: String::from("order"),
+            id: String::from(id),
+            sql: String::from(sql),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn detects_oracle_constructs() {
+        let features = detect(
+            "SELECT /*+ INDEX(o) */ NVL(a, 0), seq.NEXTVAL FROM o, c WHERE o.id = c.id(+) AND ROWNUM < 10 AND x || y = 'a' AND o.rownum = 1",
+            &DialectType::Oracle,
+        );
+        assert_eq!(
+            features,
+            [
+                Feature::Hint,
+                Feature::Nvl,
+                Feature::Sequence,
+                Feature::OuterJoinPlus,
+                Feature::Rownum,
+                Feature::PipeConcat
+            ]
+        );
+        // 不是调用的 `NVL` 与字面量中的关键字不算
+        assert!(detect("SELECT nvl FROM t WHERE a = 'ROWNUM'", &DialectType::Oracle).is_empty());
+    }
+
+    #[test]
+    fn hints_are_supported_by_mysql() {
+        for target in [
+            DialectType::MySQL,
+            DialectType::OceanBaseMySql,
+            DialectType::Dm,
+        ] {
+            assert!(Feature::Hint.supported_by(&target));
+        }
+        assert!(!Feature::Hint.supported_by(&DialectType::Kingbase));
+    }
+
+    #[test]
+    fn check_counts_findings_by_file_and_feature() {
+        let sqls = [
+            stat(
+                "a",
+                "SELECT /*+ FULL(t) */ NVL(a, 0) FROM t WHERE ROWNUM < 2 AND ROWNUM > 0",
+            ),
+            stat("b", "SELECT a FROM t"),
+            stat("c", "SELECT SYSDATE FROM DUAL"),
+        ];
+        let report = check(&sqls, &DialectType::Oracle, &DialectType::MySQL);
+        assert_eq!((report.total, report.incompatible), (3, 2));
+        assert_eq!(report.findings[0].key, "order.a");
+        assert_eq!(report.findings[0].features, ["NVL", "ROWNUM"]);
+        assert_eq!(report.by_feature["ROWNUM"], 2);
+        assert_eq!(report.by_file["order.xml"]["SYSDATE"], 1);
+        assert!(!report.by_feature.contains_key("hint"));
+        let report = check(&sqls, &DialectType::Oracle, &DialectType::OpenGauss);
+        assert_eq!(report.by_feature["hint"], 1);
+    }
+}
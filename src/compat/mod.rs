@@ -0,0 +1,2 @@
+/// 方言兼容性检查
+pub mod compat_checker;
@@ -0,0 +1,97 @@
+use log::{info, warn};
+use std::{
+    env, fs,
+    path::Path,
+    process::{self, Command, Stdio},
+};
+
+/// 比对用的源码树。`git` 版本会导出到临时目录，用完即删
+pub struct SourceTree {
+    pub dir: String,
+    temporary: bool,
+}
+
+impl Drop for SourceTree {
+    fn drop(&mut self) {
+        if self.temporary && fs::remove_dir_all(&self.dir).is_err() {
+            warn!("remove temporary directory [{}] failed", self.dir);
+        }
+    }
+}
+
+/// 已存在的目录直接使用，否则视作当前仓库中的 `git` 版本，导出到临时目录
+pub fn prepare(spec: &str, tag: &str) -> SourceTree {
+    if Path::new(spec).is_dir() {
+        return SourceTree {
+            dir: spec.to_string(),
+            temporary: false,
+        };
+    }
+    let dir = env::temp_dir()
+        .join(format!("xbatis2sql-{}-{tag}", process::id()))
+        .to_string_lossy()
+        .to_string();
+    info!("export git revision [{spec}] to [{dir}]");
+    if fs::create_dir_all(&dir).is_err() {
+        warn!("create temporary directory [{dir}] failed");
+        process::exit(-1);
+    }
+    let tree = SourceTree {
+        dir,
+        temporary: true,
+    };
+    if !export_revision(spec, &tree.dir) {
+        warn!("[{spec}] is neither a directory nor a git revision");
+        drop(tree);
+        process::exit(-1);
+    }
+    tree
+}
+
+/// `git archive <rev> | tar -x -C <dir>`
+fn export_revision(rev: &str, dir: &str) -> bool {
+    let git = Command::new("git")
+        .args(["archive", "--format=tar", rev])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let mut git = match git {
+        Ok(git) => git,
+        Err(e) => {
+            warn!("run git failed: {e}");
+            return false;
+        }
+    };
+    let tar = match git.stdout.take() {
+        Some(stdout) => Command::new("tar")
+            .args(["-x", "-C", dir])
+            .stdin(stdout)
+            .status(),
+        None => return false,
+    };
+    let git_ok = git.wait().map(|s| s.success()).unwrap_or(false);
+    let tar_ok = tar.map(|s| s.success()).unwrap_or(false);
+    git_ok && tar_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn directories_are_used_in_place() {
+        let tree = prepare("src", "dir");
+        assert_eq!(tree.dir, "src");
+        drop(tree);
+        assert!(Path::new("src").is_dir());
+    }
+
+    #[test]
+    fn git_revisions_are_exported_and_removed() {
+        let tree = prepare("HEAD", "rev");
+        let dir = tree.dir.clone();
+        assert!(Path::new(&dir).join("Cargo.toml").is_file());
+        drop(tree);
+        assert!(!Path::new(&dir).exists());
+    }
+}
@@ -0,0 +1,4 @@
+/// 准备比对用的源码树，支持目录或 `git` 版本
+pub mod git_tree;
+/// 比对抽取出的 `sql`
+pub mod sql_diff;
@@ -0,0 +1,241 @@
+use crate::extract::sql_extractor::ExtractedSql;
+use log::warn;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// 新增或删除的语句
+#[derive(Serialize)]
+pub struct DiffItem {
+    pub key: String,
+    pub file: String,
+    pub sql: String,
+}
+
+/// 变化的语句
+#[derive(Serialize)]
+pub struct ChangedItem {
+    pub key: String,
+    pub base_file: String,
+    pub head_file: String,
+    pub base_sql: String,
+    pub head_sql: String,
+    /// 按词比对的结果，`[-删除-]{+新增+}`
+    pub diff: String,
+}
+
+/// 比对报告，均按命名空间 + `id` 排序
+#[derive(Serialize, Default)]
+pub struct DiffReport {
+    pub added: Vec<DiffItem>,
+    pub removed: Vec<DiffItem>,
+    pub changed: Vec<ChangedItem>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// 以命名空间 + `id` 为键比对两组语句
+pub fn diff(base: &[ExtractedSql], head: &[ExtractedSql]) -> DiffReport {
+    let base_map = index(base, "base");
+    let head_map = index(head, "head");
+    let mut report = DiffReport::default();
+    for (key, b) in base_map.iter() {
+        match head_map.get(key) {
+            Some(h) if h.sql != b.sql => report.changed.push(ChangedItem {
+                key: key.clone(),
+                base_file: b.file.clone(),
+                head_file: h.file.clone(),
+                base_sql: b.sql.clone(),
+                head_sql: h.sql.clone(),
+                diff: word_diff(&b.sql, &h.sql),
+            }),
+            Some(_) => {}
+            None => report.removed.push(to_item(key, b)),
+        }
+    }
+    for (key, h) in head_map.iter() {
+        if !base_map.contains_key(key) {
+            report.added.push(to_item(key, h));
+        }
+    }
+    report
+}
+
+fn index<'a>(stats: &'a [ExtractedSql], side: &str) -> BTreeMap<String, &'a ExtractedSql> {
+    let mut map = BTreeMap::new();
+    for stat in stats {
+        if let Some(prev) = map.insert(stat.key(), stat) {
+            warn!(
+                "duplicated statement [{}] in {side}: {} and {}",
+                stat.key(),
+                prev.file,
+                stat.file
+            );
+        }
+    }
+    map
+}
+
+fn to_item(key: &str, stat: &ExtractedSql) -> DiffItem {
+    DiffItem {
+        key: key.to_string(),
+        file: stat.file.clone(),
+        sql: stat.sql.clone(),
+    }
+}
+
+/// 按词做最长公共子序列比对
+fn word_diff(base: &str, head: &str) -> String {
+    let a: Vec<&str> = base.split_whitespace().collect();
+    let b: Vec<&str> = head.split_whitespace().collect();
+    let (n, m) = (a.len(), b.len());
+    let mut lcs = vec![vec![0u32; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let mut out: Vec<String> = Vec::new();
+    let (mut removed, mut added): (Vec<&str>, Vec<&str>) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && a[i] == b[j] {
+            flush_changes(&mut out, &mut removed, &mut added);
+            out.push(a[i].to_string());
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            added.push(b[j]);
+            j += 1;
+        } else {
+            removed.push(a[i]);
+            i += 1;
+        }
+    }
+    flush_changes(&mut out, &mut removed, &mut added);
+    out.join(" ")
+}
+
+fn flush_changes(out: &mut Vec<String>, removed: &mut Vec<&str>, added: &mut Vec<&str>) {
+    if !removed.is_empty() {
+        out.push(format!("[-{}-]", removed.join(" ")));
+        removed.clear();
+    }
+    if !added.is_empty() {
+        out.push(format!("{{+{}+}}", added.join(" ")));
+        added.clear();
+    }
+}
+
+/// 文本格式输出
+pub fn print_text(report: &DiffReport) {
+    if report.is_empty() {
+        println!("no difference");
+        return;
+    }
+    for item in report.added.iter() {
+        println!("+ {} ({})", item.key, item.file);
+        println!("    {}", item.sql);
+    }
+    for item in report.removed.iter() {
+        println!("- {} ({})", item.key, item.file);
+        println!("    {}", item.sql);
+    }
+    for item in report.changed.iter() {
+        println!("~ {} ({})", item.key, item.head_file);
+        println!("    {}", item.diff);
+    }
+    println!(
+        "added: {}, removed: {}, changed: {}",
+        report.added.len(),
+        report.removed.len(),
+        report.changed.len()
+    );
+}
+
+/// 简要输出，只列出语句标识，供监听模式使用
+pub fn print_summary(report: &DiffReport) {
+    for item in report.added.iter() {
+        println!("+ {}", item.key);
+    }
+    for item in report.removed.iter() {
+        println!("- {}", item.key);
+    }
+    for item in report.changed.iter() {
+        println!("~ {}", item.key);
+    }
+    println!(
+        "added: {}, removed: {}, changed: {}",
+        report.added.len(),
+        report.removed.len(),
+        report.changed.len()
+    );
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &DiffReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize diff report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(namespace: &str, id: &str, sql: &str) -> ExtractedSql {
+        ExtractedSql {
+            file: format!("{namespace}.xml"),
+            namespace: String::from(namespace),
+            id: String::from(id),
+            sql: String::from(sql),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn word_diff_marks_removed_and_added_words() {
+        assert_eq!(
+            word_diff(
+                "SELECT a FROM t WHERE id = ?",
+                "SELECT a, b FROM t WHERE id = ?"
+            ),
+            "SELECT [-a-] {+a, b+} FROM t WHERE id = ?"
+        );
+        assert_eq!(
+            word_diff("DELETE FROM t", "DELETE FROM t WHERE id = ?"),
+            "DELETE FROM t {+WHERE id = ?+}"
+        );
+        assert_eq!(word_diff("SELECT 1", "SELECT 1"), "SELECT 1");
+    }
+
+    #[test]
+    fn statements_are_matched_by_namespace_and_id() {
+        let base = [
+            stat("m", "kept", "SELECT 1"),
+            stat("m", "changed", "SELECT a FROM t"),
+            stat("m", "dropped", "SELECT 2"),
+        ];
+        let head = [
+            stat("m", "kept", "SELECT 1"),
+            stat("m", "changed", "SELECT b FROM t"),
+            stat("n", "dropped", "SELECT 2"),
+        ];
+        let report = diff(&base, &head);
+        let keys = |items: &[DiffItem]| items.iter().map(|i| i.key.clone()).collect::<Vec<_>>();
+        assert_eq!(keys(&report.added), ["n.dropped"]);
+        assert_eq!(keys(&report.removed), ["m.dropped"]);
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(report.changed[0].key, "m.changed");
+        assert_eq!(report.changed[0].diff, "SELECT [-a-] {+b+} FROM t");
+        assert!(diff(&base, &base).is_empty());
+    }
+}
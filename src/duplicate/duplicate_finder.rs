@@ -0,0 +1,488 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    save::csv_writer,
+    sql::lexer::{self, TemplateSyntax, TokenKind},
+    validate::sql_validator,
+    xbatis::def::{DialectType, Mode},
+};
+use log::warn;
+use serde::Serialize;
+use sqlparser::ast::{
+    AssignmentTarget, Expr, Ident, ObjectName, ObjectNamePart, Query, SelectItem, SetExpr,
+    Statement, TableFactor, Value, VisitMut, VisitorMut,
+};
+use std::{cmp::Reverse, collections::HashMap, ops::ControlFlow};
+
+/// 组内的语句
+#[derive(Serialize)]
+pub struct Member {
+    pub file: String,
+    pub line: u64,
+    pub key: String,
+}
+
+/// 一组重复的语句
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    /// 组内两两相似度的最小值，完全相同为 `1`
+    pub similarity: f64,
+    pub members: Vec<Member>,
+    /// 第一条语句的规范形式
+    pub sql: String,
+}
+
+/// 重复语句报告
+#[derive(Serialize, Default)]
+pub struct DuplicateReport {
+    pub total: usize,
+    pub threshold: f64,
+    /// 归入各组的语句数
+    pub grouped: usize,
+    pub groups: Vec<DuplicateGroup>,
+}
+
+/// 规范形式相同的语句归为一组；`threshold` 小于 `1` 时按全连接聚类合并相近的组，
+/// 组内任意两条语句的相似度都不低于阈值；取键语句不计
+pub fn find(
+    sqls: &[ExtractedSql],
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+    threshold: f64,
+) -> DuplicateReport {
+    let mut report = DuplicateReport {
+        threshold,
+        ..Default::default()
+    };
+    // 规范形式去重，完全相同的语句共用一项
+    let mut forms: Vec<Form> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut interner: HashMap<String, u32> = HashMap::new();
+    for stat in sqls {
+        if stat.kind == Mode::SelectKey.name() {
+            continue;
+        }
+        report.total += 1;
+        let sql = canonical(&stat.template, dialect_type, syntax);
+        let i = *index.entry(sql.clone()).or_insert_with(|| {
+            forms.push(Form::new(sql, dialect_type, syntax, &mut interner));
+            forms.len() - 1
+        });
+        forms[i].members.push(stat);
+    }
+
+    let similar = if threshold < 1.0 {
+        similar_pairs(&forms, threshold)
+    } else {
+        HashMap::new()
+    };
+    for members in cluster(forms.len(), &similar) {
+        let stats: Vec<&ExtractedSql> = members
+            .iter()
+            .flat_map(|i| forms[*i].members.iter().copied())
+            .collect();
+        if stats.len() < 2 {
+            continue;
+        }
+        let mut similarity: f64 = 1.0;
+        for (n, i) in members.iter().enumerate() {
+            for j in members[n + 1..].iter() {
+                similarity = similarity.min(similar[&(*i.min(j), *i.max(j))]);
+            }
+        }
+        report.grouped += stats.len();
+        report.groups.push(DuplicateGroup {
+            similarity: (similarity * 100.0).round() / 100.0,
+            members: stats
+                .iter()
+                .map(|stat| Member {
+                    file: stat.file.clone(),
+                    line: stat.line,
+                    key: stat.key(),
+                })
+                .collect(),
+            sql: forms[members[0]].sql.clone(),
+        });
+    }
+    report
+        .groups
+        .sort_by_key(|group| Reverse(group.members.len()));
+    report
+}
+
+/// 相似度不低于阈值的两两组合，键中序号小的在前；
+/// 只比较语句类型相同的，按二元组数量排序后，长度相差过大即不再向后比较
+fn similar_pairs(forms: &[Form], threshold: f64) -> HashMap<(usize, usize), f64> {
+    let mut buckets: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, form) in forms.iter().enumerate() {
+        buckets.entry(form.kind).or_default().push(i);
+    }
+    let mut pairs: HashMap<(usize, usize), f64> = HashMap::new();
+    for bucket in buckets.values_mut() {
+        bucket.sort_by_key(|i| forms[*i].size);
+        for (n, i) in bucket.iter().enumerate() {
+            for j in bucket[n + 1..].iter() {
+                if !forms[*i].within_reach(&forms[*j], threshold) {
+                    break;
+                }
+                if let Some(similarity) = forms[*i].similarity(&forms[*j], threshold) {
+                    pairs.insert((*i.min(j), *i.max(j)), similarity);
+                }
+            }
+        }
+    }
+    pairs
+}
+
+/// 全连接聚类：按相似度从高到低尝试合并两组，两组间任意两项均相近时才合并；
+/// 各组按首项的序号排列
+fn cluster(n: usize, similar: &HashMap<(usize, usize), f64>) -> Vec<Vec<usize>> {
+    let mut pairs: Vec<(&(usize, usize), &f64)> = similar.iter().collect();
+    pairs.sort_by(|a, b| b.1.total_cmp(a.1).then(a.0.cmp(b.0)));
+    let mut group_of: Vec<usize> = (0..n).collect();
+    let mut groups: Vec<Vec<usize>> = (0..n).map(|i| vec![i]).collect();
+    for ((i, j), _) in pairs {
+        let (a, b) = (group_of[*i], group_of[*j]);
+        if a == b {
+            continue;
+        }
+        let linked = groups[a].iter().all(|x| {
+            groups[b]
+                .iter()
+                .all(|y| similar.contains_key(&(*x.min(y), *x.max(y))))
+        });
+        if linked {
+            let (keep, merged) = (a.min(b), a.max(b));
+            let moved = std::mem::take(&mut groups[merged]);
+            for x in moved.iter() {
+                group_of[*x] = keep;
+            }
+            groups[keep].extend(moved);
+            groups[keep].sort_unstable();
+        }
+    }
+    groups.retain(|group| !group.is_empty());
+    groups
+}
+
+/// 一种规范形式及其词的二元组
+struct Form<'a> {
+    sql: String,
+    /// 第一个词，语句类型不同时不比较
+    kind: u32,
+    bigrams: HashMap<(u32, u32), u32>,
+    size: u32,
+    members: Vec<&'a ExtractedSql>,
+}
+
+impl Form<'_> {
+    fn new(
+        sql: String,
+        dialect_type: &DialectType,
+        syntax: TemplateSyntax,
+        interner: &mut HashMap<String, u32>,
+    ) -> Self {
+        let ids: Vec<u32> = lexer::tokenize(&sql, dialect_type, syntax)
+            .iter()
+            .map(|t| {
+                let next = interner.len() as u32;
+                *interner.entry(t.text.to_uppercase()).or_insert(next)
+            })
+            .collect();
+        let mut bigrams: HashMap<(u32, u32), u32> = HashMap::new();
+        for pair in ids.windows(2) {
+            *bigrams.entry((pair[0], pair[1])).or_default() += 1;
+        }
+        Form {
+            sql,
+            kind: ids.first().copied().unwrap_or_default(),
+            size: bigrams.values().sum(),
+            bigrams,
+            members: Vec::new(),
+        }
+    }
+
+    /// 长度相差不大，`Dice` 系数有可能达到阈值
+    fn within_reach(&self, other: &Form, threshold: f64) -> bool {
+        let total = f64::from(self.size + other.size);
+        total > 0.0 && 2.0 * f64::from(self.size.min(other.size)) / total >= threshold
+    }
+
+    /// 二元组的 `Dice` 系数，低于阈值时为 `None`
+    fn similarity(&self, other: &Form, threshold: f64) -> Option<f64> {
+        if self.kind != other.kind || !self.within_reach(other, threshold) {
+            return None;
+        }
+        let total = f64::from(self.size + other.size);
+        let common: u32 = self
+            .bigrams
+            .iter()
+            .map(|(bigram, n)| other.bigrams.get(bigram).map_or(0, |m| *n.min(m)))
+            .sum();
+        let similarity = 2.0 * f64::from(common) / total;
+        (similarity >= threshold).then_some(similarity)
+    }
+}
+
+/// 规范形式：去掉别名、限定名与 `schema`，标识符转为小写，参数统一为 `?`，按语法重新输出；
+/// 无法解析时按词输出
+fn canonical(sql: &str, dialect_type: &DialectType, syntax: TemplateSyntax) -> String {
+    match sql_validator::parse(sql, dialect_type) {
+        Ok(mut statements) => {
+            let _ = statements.visit(&mut Canonicalizer);
+            statements
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        }
+        Err(_) => lexer::tokenize(sql, dialect_type, syntax)
+            .iter()
+            .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Hint))
+            .map(|t| match t.kind {
+                TokenKind::Placeholder => String::from("?"),
+                TokenKind::Word => t.text.to_uppercase(),
+                _ => t.text.clone(),
+            })
+            .collect::<Vec<String>>()
+            .join(" "),
+    }
+}
+
+struct Canonicalizer;
+
+impl VisitorMut for Canonicalizer {
+    type Break = ();
+
+    fn pre_visit_statement(&mut self, statement: &mut Statement) -> ControlFlow<Self::Break> {
+        match statement {
+            Statement::Insert(insert) => {
+                for column in insert.columns.iter_mut() {
+                    *column = lower(column.clone());
+                }
+            }
+            Statement::Update { assignments, .. } => {
+                for assignment in assignments.iter_mut() {
+                    match &mut assignment.target {
+                        AssignmentTarget::ColumnName(name) => last_part(name),
+                        AssignmentTarget::Tuple(names) => names.iter_mut().for_each(last_part),
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        drop_column_aliases(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<Self::Break> {
+        last_part(relation);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        match table_factor {
+            TableFactor::Table { alias, .. } | TableFactor::Derived { alias, .. } => *alias = None,
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::CompoundIdentifier(idents) => {
+                if let Some(ident) = idents.pop() {
+                    *expr = Expr::Identifier(lower(ident));
+                }
+            }
+            Expr::Identifier(ident) => *ident = lower(ident.clone()),
+            Expr::Value(value) => {
+                if let Value::Placeholder(p) = &mut value.value {
+                    *p = String::from("?");
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn drop_column_aliases(body: &mut SetExpr) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in select.projection.iter_mut() {
+                match item {
+                    SelectItem::ExprWithAlias { expr, .. } => {
+                        *item = SelectItem::UnnamedExpr(expr.clone());
+                    }
+                    SelectItem::QualifiedWildcard(_, options) => {
+                        *item = SelectItem::Wildcard(options.clone());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            drop_column_aliases(left);
+            drop_column_aliases(right);
+        }
+        _ => {}
+    }
+}
+
+/// 只保留名称的最后一段，去掉 `schema` 或别名限定
+fn last_part(name: &mut ObjectName) {
+    if let Some(ObjectNamePart::Identifier(ident)) = name.0.pop() {
+        name.0 = vec![ObjectNamePart::Identifier(lower(ident))];
+    }
+}
+
+fn lower(mut ident: Ident) -> Ident {
+    ident.value = ident.value.to_lowercase();
+    ident.quote_style = None;
+    ident
+}
+
+/// 文本格式输出
+pub fn print_text(report: &DuplicateReport) {
+    for group in report.groups.iter() {
+        if group.similarity >= 1.0 {
+            println!("identical ({}):", group.members.len());
+        } else {
+            println!("similar {:.2} ({}):", group.similarity, group.members.len());
+        }
+        for member in group.members.iter() {
+            println!("    {}:{}: {}", member.file, member.line, member.key);
+        }
+        println!("    {}", group.sql);
+    }
+    println!(
+        "groups: {}, duplicated statements: {} of {}",
+        report.groups.len(),
+        report.grouped,
+        report.total
+    );
+}
+
+/// `csv` 格式输出，每行为一条语句，以组号关联
+pub fn print_csv(report: &DuplicateReport) {
+    csv_writer::print_row(&["group", "similarity", "statement", "file", "line"]);
+    for (i, group) in report.groups.iter().enumerate() {
+        for member in group.members.iter() {
+            csv_writer::print_row(&[
+                (i + 1).to_string(),
+                format!("{:.2}", group.similarity),
+                member.key.clone(),
+                member.file.clone(),
+                member.line.to_string(),
+            ]);
+        }
+    }
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &DuplicateReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize duplicate report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        extract::sql_extractor,
+        xbatis::{mybatis_parser, xbatis_parser::Parser},
+    };
+
+    fn extract(statements: &str) -> Vec<ExtractedSql> {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        parser.setup_replace_num(10);
+        let xml = format!("<mapper namespace=\"m\">{statements}</mapper>");
+        let parsed = parser.parse_xml("test.xml", &mut xml.as_bytes());
+        sql_extractor::resolve_file(
+            &parser,
+            "test.xml",
+            &parsed,
+            &HashMap::new(),
+            &HashMap::new(),
+        )
+    }
+
+    fn groups(report: &DuplicateReport) -> Vec<(f64, Vec<&str>)> {
+        report
+            .groups
+            .iter()
+            .map(|g| {
+                (
+                    g.similarity,
+                    g.members.iter().map(|m| m.key.as_str()).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn canonical_form_ignores_aliases_schema_case_and_placeholders() {
+        let sql =
+            |template: &str| canonical(template, &DialectType::MySQL, TemplateSyntax::MyBatis);
+        let expected = "SELECT id, * FROM users WHERE name = ? AND age > 1";
+        assert_eq!(
+            sql("SELECT T.ID AS x, t.* FROM app.USERS t WHERE t.NAME = #{name} AND `t`.age > 1"),
+            expected
+        );
+        assert_eq!(
+            sql("select id, * from users where name = #{n,jdbcType=VARCHAR} and age > 1"),
+            expected
+        );
+        assert_eq!(
+            sql("UPDATE app.Users u SET u.Name = #{name} WHERE u.ID = #{id}"),
+            "UPDATE users SET name = ? WHERE id = ?"
+        );
+    }
+
+    #[test]
+    fn identical_statements_form_one_group() {
+        let sqls = extract(
+            "<select id=\"a\">select U.ID from app.USERS u where u.name = #{name}</select>\
+             <select id=\"b\">select id from users where NAME = #{n}</select>\
+             <select id=\"c\">select id from users where age = #{age}</select>",
+        );
+        let report = find(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, 1.0);
+        assert_eq!(report.total, 3);
+        assert_eq!(report.grouped, 2);
+        assert_eq!(groups(&report), [(1.0, vec!["m.a", "m.b"])]);
+    }
+
+    /// `a`、`b` 与 `b`、`c` 和 `a`、`d` 的相似度均为 `0.88`，其余组合更低
+    const CHAIN: &str = "<select id=\"a\">select a, b, c, d, e, f from t where x = #{x}</select>\
+        <select id=\"b\">select a, b, c, d, e, g from t where x = #{x}</select>\
+        <select id=\"c\">select a, b, c, d, h, g from t where x = #{x}</select>\
+        <select id=\"d\">select a, b, c, d, e, f from t where y = #{y}</select>\
+        <delete id=\"e\">delete from t where x = #{x}</delete>";
+
+    #[test]
+    fn similar_statements_are_grouped_by_complete_linkage() {
+        let sqls = extract(CHAIN);
+        let report = find(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, 0.85);
+        assert_eq!(groups(&report), [(0.88, vec!["m.a", "m.b"])]);
+        let report = find(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, 0.6);
+        assert_eq!(groups(&report), [(0.65, vec!["m.a", "m.b", "m.c", "m.d"])]);
+    }
+
+    #[test]
+    fn threshold_keeps_its_precision() {
+        let sqls = extract(CHAIN);
+        let report = find(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, 0.882);
+        assert_eq!(report.groups.len(), 1);
+        let report = find(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, 0.8825);
+        assert!(report.groups.is_empty());
+    }
+}
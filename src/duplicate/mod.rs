@@ -0,0 +1,2 @@
+/// 语义重复的语句
+pub mod duplicate_finder;
@@ -0,0 +1,114 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// 执行计划中值得关注的操作
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+    allow(dead_code)
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagKind {
+    /// 全表扫描
+    FullTableScan,
+    /// 文件排序
+    Filesort,
+    /// 临时表
+    TemporaryTable,
+}
+
+impl FlagKind {
+    pub fn name(&self) -> &str {
+        match self {
+            FlagKind::FullTableScan => "full table scan",
+            FlagKind::Filesort => "filesort",
+            FlagKind::TemporaryTable => "temporary table",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct PlanFlag {
+    pub kind: FlagKind,
+    /// 涉及的表，执行计划未给出时为 `None`
+    pub table: Option<String>,
+}
+
+impl PlanFlag {
+    #[cfg_attr(
+        not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+        allow(dead_code)
+    )]
+    pub fn new(kind: FlagKind, table: &str) -> Self {
+        PlanFlag {
+            kind,
+            table: Some(table.to_string()).filter(|t| !t.is_empty()),
+        }
+    }
+}
+
+/// 可执行 `EXPLAIN` 的数据库
+pub trait Database {
+    /// 执行 `EXPLAIN`，以 `json` 返回执行计划
+    fn explain(&mut self, sql: &str) -> Result<Value, String>;
+
+    /// 从执行计划中找出全表扫描、文件排序与临时表
+    fn inspect(&self, plan: &Value) -> Vec<PlanFlag>;
+}
+
+/// 按 `url` 的协议连接数据库：`mysql://`、`postgres://`、`sqlite://PATH` 或 `sqlite::memory:`
+pub fn connect(url: &str) -> Result<Box<dyn Database>, String> {
+    match url.split(':').next().unwrap_or("") {
+        "mysql" => connect_mysql(url),
+        "postgres" | "postgresql" => connect_postgres(url),
+        "sqlite" => connect_sqlite(url),
+        _ => Err(format!("unsupported database url: {url}")),
+    }
+}
+
+#[cfg(feature = "mysql")]
+fn connect_mysql(url: &str) -> Result<Box<dyn Database>, String> {
+    super::mysql_db::connect(url)
+}
+
+#[cfg(not(feature = "mysql"))]
+fn connect_mysql(_url: &str) -> Result<Box<dyn Database>, String> {
+    Err(not_built("mysql"))
+}
+
+#[cfg(feature = "postgres")]
+fn connect_postgres(url: &str) -> Result<Box<dyn Database>, String> {
+    super::postgres_db::connect(url)
+}
+
+#[cfg(not(feature = "postgres"))]
+fn connect_postgres(_url: &str) -> Result<Box<dyn Database>, String> {
+    Err(not_built("postgres"))
+}
+
+#[cfg(feature = "sqlite")]
+fn connect_sqlite(url: &str) -> Result<Box<dyn Database>, String> {
+    super::sqlite_db::connect(url)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn connect_sqlite(_url: &str) -> Result<Box<dyn Database>, String> {
+    Err(not_built("sqlite"))
+}
+
+#[cfg_attr(
+    all(feature = "mysql", feature = "postgres", feature = "sqlite"),
+    allow(dead_code)
+)]
+fn not_built(feature: &str) -> String {
+    format!("built without {feature} support, rebuild with `--features {feature}`")
+}
+
+/// 执行计划行中的文本字段，缺失时为空串
+#[cfg_attr(
+    not(any(feature = "mysql", feature = "postgres", feature = "sqlite")),
+    allow(dead_code)
+)]
+pub(super) fn field<'a>(row: &'a Value, key: &str) -> &'a str {
+    row.get(key).and_then(Value::as_str).unwrap_or("")
+}
@@ -0,0 +1,13 @@
+/// 数据库连接与执行计划解读
+pub mod database;
+/// `MySQL` 连接
+#[cfg(feature = "mysql")]
+pub mod mysql_db;
+/// 逐条执行 `EXPLAIN` 并汇总执行计划
+pub mod plan_runner;
+/// `PostgreSQL` 连接
+#[cfg(feature = "postgres")]
+pub mod postgres_db;
+/// `SQLite` 连接
+#[cfg(feature = "sqlite")]
+pub mod sqlite_db;
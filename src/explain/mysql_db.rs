@@ -0,0 +1,63 @@
+use super::database::{field, Database, FlagKind, PlanFlag};
+use mysql::{prelude::Queryable, Conn, Opts, Row, Value as MyValue};
+use serde_json::{Map, Value};
+
+pub struct MySqlDatabase {
+    conn: Conn,
+}
+
+pub fn connect(url: &str) -> Result<Box<dyn Database>, String> {
+    let opts = Opts::from_url(url).map_err(|e| e.to_string())?;
+    let conn = Conn::new(opts).map_err(|e| e.to_string())?;
+    Ok(Box::new(MySqlDatabase { conn }))
+}
+
+impl Database for MySqlDatabase {
+    fn explain(&mut self, sql: &str) -> Result<Value, String> {
+        let rows: Vec<Row> = self
+            .conn
+            .query(format!("EXPLAIN {sql}"))
+            .map_err(|e| e.to_string())?;
+        Ok(Value::Array(rows.into_iter().map(row_to_json).collect()))
+    }
+
+    /// `type = ALL` 为全表扫描，`Extra` 中标明文件排序与临时表
+    fn inspect(&self, plan: &Value) -> Vec<PlanFlag> {
+        let mut flags = Vec::new();
+        for row in plan.as_array().into_iter().flatten() {
+            let table = field(row, "table");
+            let extra = field(row, "Extra");
+            if field(row, "type") == "ALL" {
+                flags.push(PlanFlag::new(FlagKind::FullTableScan, table));
+            }
+            if extra.contains("Using filesort") {
+                flags.push(PlanFlag::new(FlagKind::Filesort, table));
+            }
+            if extra.contains("Using temporary") {
+                flags.push(PlanFlag::new(FlagKind::TemporaryTable, table));
+            }
+        }
+        flags
+    }
+}
+
+fn row_to_json(row: Row) -> Value {
+    let columns = row.columns();
+    let mut map = Map::new();
+    for (column, value) in columns.iter().zip(row.unwrap()) {
+        map.insert(column.name_str().to_string(), to_json(value));
+    }
+    Value::Object(map)
+}
+
+fn to_json(value: MyValue) -> Value {
+    match value {
+        MyValue::NULL => Value::Null,
+        MyValue::Bytes(bytes) => Value::String(String::from_utf8_lossy(&bytes).to_string()),
+        MyValue::Int(i) => Value::from(i),
+        MyValue::UInt(u) => Value::from(u),
+        MyValue::Float(f) => Value::from(f),
+        MyValue::Double(d) => Value::from(d),
+        other => Value::String(other.as_sql(true)),
+    }
+}
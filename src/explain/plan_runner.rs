@@ -0,0 +1,131 @@
+use super::database::{Database, PlanFlag};
+use crate::extract::sql_extractor::ExtractedSql;
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::BTreeMap, fs, path::Path};
+
+/// 单条语句的执行计划
+#[derive(Serialize)]
+pub struct StatementPlan {
+    pub file: String,
+    pub sql: String,
+    /// 数据库返回的执行计划，执行失败时为 `None`
+    pub plan: Option<Value>,
+    pub flags: Vec<PlanFlag>,
+    pub error: Option<String>,
+}
+
+/// 执行计划报告，按语句标识排序
+#[derive(Serialize, Default)]
+pub struct PlanReport {
+    pub total: usize,
+    pub failed: usize,
+    pub flagged: usize,
+    pub plans: BTreeMap<String, StatementPlan>,
+}
+
+/// 逐条执行 `EXPLAIN`，单条失败不影响其余语句
+pub fn run(db: &mut dyn Database, sqls: &[ExtractedSql]) -> PlanReport {
+    let mut report = PlanReport {
+        total: sqls.len(),
+        ..Default::default()
+    };
+    for stat in sqls {
+        let sql = executable(&stat.sql);
+        let (plan, flags, error) = match db.explain(&sql) {
+            Ok(plan) => {
+                let flags = db.inspect(&plan);
+                (Some(plan), flags, None)
+            }
+            Err(e) => (None, Vec::new(), Some(e)),
+        };
+        if error.is_some() {
+            report.failed += 1;
+        }
+        if !flags.is_empty() {
+            report.flagged += 1;
+        }
+        report.plans.insert(
+            stat.key(),
+            StatementPlan {
+                file: stat.file.clone(),
+                sql,
+                plan,
+                flags,
+                error,
+            },
+        );
+    }
+    info!(
+        "explained {} statements, {} failed",
+        report.total, report.failed
+    );
+    report
+}
+
+/// 去掉 `schema` 替代符与结尾的分号，交由连接的默认 `schema` 解析
+fn executable(sql: &str) -> String {
+    sql.replace("__REPLACE_SCHEMA__.", "")
+        .trim_end()
+        .trim_end_matches(';')
+        .to_string()
+}
+
+/// 保存为 `OUTPUT/plans.json`
+pub fn save(report: &PlanReport, output_dir: &str) {
+    let path = Path::new(output_dir).join("plans.json");
+    let result = fs::create_dir_all(output_dir).and_then(|_| {
+        let json = serde_json::to_string_pretty(report).map_err(std::io::Error::other)?;
+        fs::write(&path, json)
+    });
+    match result {
+        Ok(_) => info!("plans saved to {path:?}"),
+        Err(e) => warn!("save plans to {path:?} failed: {e}"),
+    }
+}
+
+/// 文本格式输出，只列出有问题的语句
+pub fn print_text(report: &PlanReport) {
+    for (key, stat) in report.plans.iter() {
+        if let Some(error) = &stat.error {
+            println!("{}: {} error: {}", stat.file, key, error);
+        } else if !stat.flags.is_empty() {
+            let flags: Vec<String> = stat
+                .flags
+                .iter()
+                .map(|f| match &f.table {
+                    Some(table) => format!("{} on {}", f.kind.name(), table),
+                    None => String::from(f.kind.name()),
+                })
+                .collect();
+            println!("{}: {} {}", stat.file, key, flags.join(", "));
+        }
+    }
+    println!(
+        "explained: {}, failed: {}, flagged: {}",
+        report.total, report.failed, report.flagged
+    );
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &PlanReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize plan report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executable_strips_schema_and_semicolon() {
+        assert_eq!(
+            executable("SELECT * FROM __REPLACE_SCHEMA__.orders WHERE id = 1; \n"),
+            "SELECT * FROM orders WHERE id = 1"
+        );
+        assert_eq!(executable("SELECT ';'"), "SELECT ';'");
+    }
+}
@@ -0,0 +1,58 @@
+use super::database::{field, Database, FlagKind, PlanFlag};
+use postgres::{Client, NoTls, SimpleQueryMessage};
+use serde_json::Value;
+
+pub struct PostgresDatabase {
+    client: Client,
+}
+
+pub fn connect(url: &str) -> Result<Box<dyn Database>, String> {
+    let client = Client::connect(url, NoTls).map_err(|e| e.to_string())?;
+    Ok(Box::new(PostgresDatabase { client }))
+}
+
+impl Database for PostgresDatabase {
+    fn explain(&mut self, sql: &str) -> Result<Value, String> {
+        let messages = self
+            .client
+            .simple_query(&format!("EXPLAIN (FORMAT JSON) {sql}"))
+            .map_err(|e| e.to_string())?;
+        for message in messages {
+            if let SimpleQueryMessage::Row(row) = message {
+                if let Some(text) = row.get(0) {
+                    return serde_json::from_str(text).map_err(|e| e.to_string());
+                }
+            }
+        }
+        Err(String::from("no plan returned"))
+    }
+
+    /// `Seq Scan` 为全表扫描，`Sort` 视为文件排序，`Materialize` 视为临时表
+    fn inspect(&self, plan: &Value) -> Vec<PlanFlag> {
+        let mut flags = Vec::new();
+        for root in plan.as_array().into_iter().flatten() {
+            if let Some(node) = root.get("Plan") {
+                walk(node, &mut flags);
+            }
+        }
+        flags
+    }
+}
+
+fn walk(node: &Value, flags: &mut Vec<PlanFlag>) {
+    let table = field(node, "Relation Name");
+    match field(node, "Node Type") {
+        "Seq Scan" => flags.push(PlanFlag::new(FlagKind::FullTableScan, table)),
+        "Sort" | "Incremental Sort" => flags.push(PlanFlag::new(FlagKind::Filesort, table)),
+        "Materialize" => flags.push(PlanFlag::new(FlagKind::TemporaryTable, table)),
+        _ => {}
+    }
+    for child in node
+        .get("Plans")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        walk(child, flags);
+    }
+}
@@ -0,0 +1,119 @@
+use super::database::{field, Database, FlagKind, PlanFlag};
+use rusqlite::Connection;
+use serde_json::{json, Value};
+
+pub struct SqliteDatabase {
+    conn: Connection,
+}
+
+/// `sqlite://PATH` 或 `sqlite::memory:`
+pub fn connect(url: &str) -> Result<Box<dyn Database>, String> {
+    let path = url
+        .strip_prefix("sqlite://")
+        .or_else(|| url.strip_prefix("sqlite:"))
+        .unwrap_or(url);
+    let conn = if path == ":memory:" {
+        Connection::open_in_memory()
+    } else {
+        Connection::open(path)
+    }
+    .map_err(|e| e.to_string())?;
+    Ok(Box::new(SqliteDatabase { conn }))
+}
+
+impl Database for SqliteDatabase {
+    fn explain(&mut self, sql: &str) -> Result<Value, String> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("EXPLAIN QUERY PLAN {sql}"))
+            .map_err(|e| e.to_string())?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(json!({
+                    "id": r.get::<_, i64>(0)?,
+                    "parent": r.get::<_, i64>(1)?,
+                    "detail": r.get::<_, String>(3)?,
+                }))
+            })
+            .map_err(|e| e.to_string())?;
+        let rows: Result<Vec<Value>, _> = rows.collect();
+        rows.map(Value::Array).map_err(|e| e.to_string())
+    }
+
+    /// 不带索引的 `SCAN` 为全表扫描，`TEMP B-TREE` 用于排序或分组、去重
+    fn inspect(&self, plan: &Value) -> Vec<PlanFlag> {
+        let mut flags = Vec::new();
+        for row in plan.as_array().into_iter().flatten() {
+            let detail = field(row, "detail");
+            if let Some(rest) = detail.strip_prefix("SCAN ") {
+                if !rest.contains(" USING ") {
+                    let table = rest.split_whitespace().next().unwrap_or("");
+                    flags.push(PlanFlag::new(FlagKind::FullTableScan, table));
+                }
+            } else if detail == "USE TEMP B-TREE FOR ORDER BY" {
+                flags.push(PlanFlag::new(FlagKind::Filesort, ""));
+            } else if detail.starts_with("USE TEMP B-TREE FOR ") {
+                flags.push(PlanFlag::new(FlagKind::TemporaryTable, ""));
+            }
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{explain::plan_runner, extract::sql_extractor::ExtractedSql};
+
+    fn stat(id: &str, sql: &str) -> ExtractedSql {
+        ExtractedSql {
+            file: String::from("order.xml"),
+            namespace: String::from("order"),
+            id: String::from(id),
+            sql: String::from(sql),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn explain_run_flags_scans_and_temp_b_trees() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE orders (id INTEGER PRIMARY KEY, customer TEXT, total REAL);
+             CREATE INDEX idx_orders_customer ON orders (customer);",
+        )
+        .unwrap();
+        let mut db = SqliteDatabase { conn };
+        let sqls = [
+            stat(
+                "sorted",
+                "SELECT * FROM __REPLACE_SCHEMA__.orders ORDER BY total;",
+            ),
+            stat(
+                "grouped",
+                "SELECT total, COUNT(*) FROM orders GROUP BY total;",
+            ),
+            stat("indexed", "SELECT * FROM orders WHERE customer = 'a';"),
+            stat("broken", "SELECT * FROM missing;"),
+        ];
+        let report = plan_runner::run(&mut db, &sqls);
+        assert_eq!((report.total, report.failed, report.flagged), (4, 1, 2));
+        let kinds = |key: &str| -> Vec<FlagKind> {
+            report.plans[key].flags.iter().map(|f| f.kind).collect()
+        };
+        assert_eq!(
+            kinds("order.sorted"),
+            [FlagKind::FullTableScan, FlagKind::Filesort]
+        );
+        assert_eq!(
+            report.plans["order.sorted"].flags[0].table.as_deref(),
+            Some("orders")
+        );
+        assert_eq!(
+            kinds("order.grouped"),
+            [FlagKind::FullTableScan, FlagKind::TemporaryTable]
+        );
+        assert!(kinds("order.indexed").is_empty());
+        assert!(report.plans["order.broken"].error.is_some());
+    }
+}
@@ -0,0 +1,2 @@
+/// 抽取结构化的语句，供比对与分析使用
+pub mod sql_extractor;
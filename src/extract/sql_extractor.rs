@@ -0,0 +1,212 @@
+use crate::xbatis::{
+    def::{DynamicNode, Mode, ParsedXml},
+    xbatis_parser::{self, Parser},
+};
+use lazy_static::lazy_static;
+use log::{info, warn};
+use regex::Regex;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, process,
+};
+
+lazy_static! {
+    static ref INCLUDE_REGEX: Regex = Regex::new("__INCLUDE_ID_(.+?)_END__").unwrap_or_else(|e| {
+        warn!("Unable to parse the regex: {e}");
+        process::exit(-1);
+    });
+}
+
+/// 语句展开的 `<sql>` 片段
+#[derive(Clone, Default)]
+pub struct Fragment {
+    /// `refid`，跨文件时带命名空间
+    pub id: String,
+    /// 片段原文，未规整
+    pub sql: String,
+    /// 引用的层数，语句直接引用的为 `1`
+    pub depth: usize,
+    /// 片段的动态元素结构，根为 `<sql>` 本身
+    pub dynamic: DynamicNode,
+}
+
+/// 抽取出的单条语句，`include` 均已替换
+#[derive(Clone, Default, Serialize)]
+pub struct ExtractedSql {
+    /// 所在文件
+    pub file: String,
+    /// 命名空间
+    pub namespace: String,
+    /// 语句 `id`，取键语句为 `<id>.selectKey`
+    pub id: String,
+    /// 语句类型
+    pub kind: String,
+    /// 规整后的 `sql`
+    pub sql: String,
+    /// 中间形式的 `sql`，占位符尚未替换，供语法解析
+    #[serde(skip)]
+    pub template: String,
+    /// 在文件中的行号，从 `1` 开始
+    pub line: u64,
+    /// 语句自身的原文，不含 `include` 展开的部分
+    #[serde(skip)]
+    pub own_sql: String,
+    /// 展开的 `<sql>` 片段，含片段中嵌套的引用
+    #[serde(skip)]
+    pub fragments: Vec<Fragment>,
+    /// 动态元素结构，取键语句为空
+    #[serde(skip)]
+    pub dynamic: DynamicNode,
+}
+
+impl ExtractedSql {
+    /// 唯一标识：命名空间 + `id`
+    pub fn key(&self) -> String {
+        xbatis_parser::full_stat_id(&self.namespace, &self.id)
+    }
+
+    /// 按 `refid` 找展开的片段，本文件的片段带命名空间
+    pub fn fragment(&self, refid: &str) -> Option<&Fragment> {
+        let local = format!("{}.{refid}", self.namespace);
+        self.fragments
+            .iter()
+            .find(|f| f.id == refid || f.id == local)
+    }
+}
+
+/// 解析全部文件，全部文件共用同一个 `include` 命名空间
+pub fn extract(parser: &dyn Parser, files: &[String]) -> Vec<ExtractedSql> {
+    let mut parsed_files: Vec<(String, ParsedXml)> = Vec::new();
+    let mut global_inc_map: HashMap<String, String> = HashMap::new();
+    let mut global_trees: HashMap<String, DynamicNode> = HashMap::new();
+    for file in files {
+        if let Some(parsed) = parse_file(parser, file) {
+            register_sql_parts(&mut global_inc_map, &mut global_trees, &parsed);
+            parsed_files.push((file.clone(), parsed));
+        }
+    }
+    let mut extracted = Vec::new();
+    for (file, parsed) in parsed_files.iter() {
+        extracted.append(&mut resolve_file(
+            parser,
+            file,
+            parsed,
+            &global_inc_map,
+            &global_trees,
+        ));
+    }
+    info!("extracted {} statements", extracted.len());
+    extracted
+}
+
+/// 解析单个文件，不是当前模式下的 `xml` 时返回 `None`
+pub fn parse_file(parser: &dyn Parser, file: &String) -> Option<ParsedXml> {
+    if !parser.detect_match(file) {
+        return None;
+    }
+    info!("try to parse [{file}]");
+    match fs::File::open(file) {
+        Ok(mut reader) => Some(parser.parse_xml(file, &mut reader)),
+        Err(e) => {
+            warn!("open file [{file}] failed: {e}");
+            None
+        }
+    }
+}
+
+/// 将文件内的 `<sql>` 片段及其动态元素结构以 `命名空间.id` 登记到全局
+pub fn register_sql_parts(
+    global_inc_map: &mut HashMap<String, String>,
+    global_trees: &mut HashMap<String, DynamicNode>,
+    parsed: &ParsedXml,
+) {
+    for (id, sql) in parsed.sql_parts.iter() {
+        global_inc_map.insert(format!("{}.{}", parsed.namespace, id), sql.clone());
+    }
+    for part in parsed.sql_part_defs.iter() {
+        let key = format!("{}.{}", parsed.namespace, part.id);
+        global_trees.insert(key, part.dynamic.clone());
+    }
+}
+
+/// 得到单个文件内全部语句的最终 `sql`
+pub fn resolve_file(
+    parser: &dyn Parser,
+    file: &str,
+    parsed: &ParsedXml,
+    global_inc_map: &HashMap<String, String>,
+    global_trees: &HashMap<String, DynamicNode>,
+) -> Vec<ExtractedSql> {
+    let mut extracted = Vec::new();
+    for stat in parsed.statements.iter() {
+        let template = parser.resolve_template(stat, &parsed.sql_parts, global_inc_map);
+        extracted.push(ExtractedSql {
+            file: file.to_string(),
+            namespace: parsed.namespace.clone(),
+            id: stat.id.clone(),
+            kind: stat.mode.name().to_string(),
+            sql: parser.bind_stat_sql(&template, &stat.param_type).1,
+            template,
+            line: stat.line,
+            own_sql: INCLUDE_REGEX.replace_all(&stat.sql, " ").to_string(),
+            fragments: fragments_of(&stat.sql, parsed, global_inc_map, global_trees),
+            dynamic: stat.dynamic.clone(),
+        });
+        if stat.has_sql_key {
+            let template = parser.resolve_key_template(stat, global_inc_map);
+            extracted.push(ExtractedSql {
+                file: file.to_string(),
+                namespace: parsed.namespace.clone(),
+                id: stat.sql_key.key.clone(),
+                kind: Mode::SelectKey.name().to_string(),
+                sql: parser.bind_stat_sql(&template, &stat.param_type).1,
+                template,
+                line: stat.line,
+                own_sql: INCLUDE_REGEX
+                    .replace_all(&stat.sql_key.sql, " ")
+                    .to_string(),
+                fragments: fragments_of(&stat.sql_key.sql, parsed, global_inc_map, global_trees),
+                dynamic: DynamicNode::default(),
+            });
+        }
+    }
+    extracted
+}
+
+/// `sql` 引用的片段，先找本文件，再找全局；片段中的引用一并展开，每个片段只出现一次，层数取最浅的一处
+fn fragments_of(
+    sql: &str,
+    parsed: &ParsedXml,
+    global_inc_map: &HashMap<String, String>,
+    global_trees: &HashMap<String, DynamicNode>,
+) -> Vec<Fragment> {
+    let mut fragments: Vec<Fragment> = Vec::new();
+    let mut pending: VecDeque<(String, usize)> = refids(sql).map(|id| (id, 1)).collect();
+    while let Some((id, depth)) = pending.pop_front() {
+        if fragments.iter().any(|f| f.id == id) {
+            continue;
+        }
+        let found = parsed
+            .sql_parts
+            .get(&id)
+            .map(|sql| (format!("{}.{}", parsed.namespace, id), sql))
+            .or_else(|| global_inc_map.get(&id).map(|sql| (id.clone(), sql)));
+        if let Some((full_id, sql)) = found {
+            pending.extend(refids(sql).map(|id| (id, depth + 1)));
+            let dynamic = global_trees.get(&full_id).cloned().unwrap_or_default();
+            fragments.push(Fragment {
+                id: full_id,
+                sql: INCLUDE_REGEX.replace_all(sql, " ").to_string(),
+                depth,
+                dynamic,
+            });
+        }
+    }
+    fragments
+}
+
+/// `sql` 中 `include` 的 `refid`
+pub fn refids(sql: &str) -> impl Iterator<Item = String> + '_ {
+    INCLUDE_REGEX.captures_iter(sql).map(|c| c[1].to_string())
+}
@@ -0,0 +1,300 @@
+use crate::{
+    extract::sql_extractor,
+    xbatis::{
+        def::{ParsedXml, SqlPart},
+        xbatis_parser::full_stat_id,
+    },
+};
+use log::warn;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+
+/// 没有任何语句引用到的 `<sql>` 片段，只被这类片段引用的也算
+#[derive(Serialize)]
+pub struct UnusedFragment {
+    pub file: String,
+    pub id: String,
+}
+
+/// 找不到对应片段的 `include`
+#[derive(Serialize)]
+pub struct MissingInclude {
+    pub file: String,
+    /// 所在语句或片段的行号
+    pub line: u64,
+    /// 所在的语句或片段
+    pub owner: String,
+    pub refid: String,
+}
+
+/// 同一命名空间下重复的语句或片段 `id`
+#[derive(Serialize)]
+pub struct DuplicateId {
+    /// `statement` 或 `fragment`
+    pub kind: &'static str,
+    /// 首次出现时的 `命名空间.id`
+    pub key: String,
+    /// `文件:行号`
+    pub locations: Vec<String>,
+}
+
+/// 整理报告
+#[derive(Serialize, Default)]
+pub struct HygieneReport {
+    pub files: usize,
+    pub statements: usize,
+    pub fragments: usize,
+    pub unused: Vec<UnusedFragment>,
+    pub missing: Vec<MissingInclude>,
+    pub duplicates: Vec<DuplicateId>,
+}
+
+/// 重复 `id` 的登记：小写的 `命名空间.id` 到首次出现时的原样与各处位置
+type Ids = BTreeMap<String, (String, Vec<String>)>;
+
+/// 语句与片段均以 `命名空间.id` 标识，一律忽略大小写，与替换 `include` 时一致；
+/// `refid` 先找本文件、再找全局
+pub fn check(parsed_files: &[(String, ParsedXml)]) -> HygieneReport {
+    let mut report = HygieneReport {
+        files: parsed_files.len(),
+        ..Default::default()
+    };
+    // 小写的 `命名空间.id` 到所在文件的序号与各处定义
+    let mut fragments: BTreeMap<String, Vec<(usize, &SqlPart)>> = BTreeMap::new();
+    let mut fragment_ids: Ids = BTreeMap::new();
+    for (i, (file, parsed)) in parsed_files.iter().enumerate() {
+        for part in parsed.sql_part_defs.iter() {
+            let key = full_stat_id(&parsed.namespace, &part.id);
+            fragments
+                .entry(key.to_lowercase())
+                .or_default()
+                .push((i, part));
+            register(&mut fragment_ids, key, file, part.line);
+            report.fragments += 1;
+        }
+    }
+    let resolve = |parsed: &ParsedXml, refid: &str| {
+        let local = full_stat_id(&parsed.namespace, refid).to_lowercase();
+        [local, refid.to_lowercase()]
+            .into_iter()
+            .find(|key| fragments.contains_key(key))
+    };
+
+    let mut used: BTreeSet<String> = BTreeSet::new();
+    let mut pending: VecDeque<String> = VecDeque::new();
+    let mut statement_ids: Ids = BTreeMap::new();
+    for (file, parsed) in parsed_files.iter() {
+        for stat in parsed.statements.iter() {
+            report.statements += 1;
+            let key = full_stat_id(&parsed.namespace, &stat.id);
+            register(&mut statement_ids, key.clone(), file, stat.line);
+            let refids =
+                sql_extractor::refids(&stat.sql).chain(sql_extractor::refids(&stat.sql_key.sql));
+            for refid in refids {
+                match resolve(parsed, &refid) {
+                    Some(fragment) => pending.push_back(fragment),
+                    None => report.missing.push(MissingInclude {
+                        file: file.clone(),
+                        line: stat.line,
+                        owner: key.clone(),
+                        refid,
+                    }),
+                }
+            }
+        }
+        for part in parsed.sql_part_defs.iter() {
+            for refid in sql_extractor::refids(&part.sql) {
+                if resolve(parsed, &refid).is_none() {
+                    report.missing.push(MissingInclude {
+                        file: file.clone(),
+                        line: part.line,
+                        owner: full_stat_id(&parsed.namespace, &part.id),
+                        refid,
+                    });
+                }
+            }
+        }
+    }
+
+    // 从语句出发，沿片段中的引用找出全部用到的片段
+    while let Some(key) = pending.pop_front() {
+        if !used.insert(key.clone()) {
+            continue;
+        }
+        for (i, part) in fragments.get(&key).into_iter().flatten() {
+            let parsed = &parsed_files[*i].1;
+            pending.extend(sql_extractor::refids(&part.sql).filter_map(|r| resolve(parsed, &r)));
+        }
+    }
+    for (key, defs) in fragments.iter() {
+        if !used.contains(key) {
+            for (i, part) in defs.iter() {
+                let (file, parsed) = &parsed_files[*i];
+                report.unused.push(UnusedFragment {
+                    file: file.clone(),
+                    id: full_stat_id(&parsed.namespace, &part.id),
+                });
+            }
+        }
+    }
+    for (kind, ids) in [("statement", statement_ids), ("fragment", fragment_ids)] {
+        for (_, (key, locations)) in ids {
+            if locations.len() > 1 {
+                report.duplicates.push(DuplicateId {
+                    kind,
+                    key,
+                    locations,
+                });
+            }
+        }
+    }
+    report
+}
+
+/// 登记一处 `id`，以首次出现时的写法作为报告中的标识
+fn register(ids: &mut Ids, key: String, file: &str, line: u64) {
+    ids.entry(key.to_lowercase())
+        .or_insert_with(|| (key, Vec::new()))
+        .1
+        .push(format!("{file}:{line}"));
+}
+
+/// 文本格式输出
+pub fn print_text(report: &HygieneReport) {
+    for fragment in report.unused.iter() {
+        println!("{}: unused fragment {}", fragment.file, fragment.id);
+    }
+    for include in report.missing.iter() {
+        println!(
+            "{}:{}: {} includes missing fragment {}",
+            include.file, include.line, include.owner, include.refid
+        );
+    }
+    for duplicate in report.duplicates.iter() {
+        println!(
+            "{}: duplicate {} id at {}",
+            duplicate.key,
+            duplicate.kind,
+            duplicate.locations.join(", ")
+        );
+    }
+    println!(
+        "files: {}, statements: {}, fragments: {}, unused: {}, missing: {}, duplicates: {}",
+        report.files,
+        report.statements,
+        report.fragments,
+        report.unused.len(),
+        report.missing.len(),
+        report.duplicates.len()
+    );
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &HygieneReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize hygiene report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xbatis::{def::DialectType, mybatis_parser, xbatis_parser::Parser};
+
+    fn parse(files: &[(&str, &str)]) -> Vec<(String, ParsedXml)> {
+        let parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        files
+            .iter()
+            .map(|(file, xml)| {
+                (
+                    file.to_string(),
+                    parser.parse_xml(file, &mut xml.as_bytes()),
+                )
+            })
+            .collect()
+    }
+
+    fn duplicates(report: &HygieneReport) -> Vec<(&str, &str, Vec<&str>)> {
+        report
+            .duplicates
+            .iter()
+            .map(|d| {
+                let locations = d.locations.iter().map(String::as_str).collect();
+                (d.kind, d.key.as_str(), locations)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_fragments_keep_every_definition() {
+        let report = check(&parse(&[(
+            "a.xml",
+            "<mapper namespace=\"m\">\n\
+             <sql id=\"cols\">a</sql>\n\
+             <sql id=\"Cols\">b</sql>\n\
+             <select id=\"q\">select <include refid=\"cols\"/> from t</select>\n\
+             </mapper>",
+        )]));
+        assert_eq!(report.fragments, 2);
+        assert_eq!(
+            duplicates(&report),
+            [("fragment", "m.cols", vec!["a.xml:2", "a.xml:3"])]
+        );
+        assert!(report.unused.is_empty());
+    }
+
+    #[test]
+    fn statement_ids_ignore_case_like_fragments() {
+        let report = check(&parse(&[
+            (
+                "a.xml",
+                "<mapper namespace=\"m\">\n<select id=\"find\">select 1</select>\n</mapper>",
+            ),
+            (
+                "b.xml",
+                "<mapper namespace=\"M\">\n\n<select id=\"FIND\">select 2</select>\n</mapper>",
+            ),
+        ]));
+        assert_eq!(
+            duplicates(&report),
+            [("statement", "m.find", vec!["a.xml:2", "b.xml:3"])]
+        );
+    }
+
+    #[test]
+    fn ids_without_namespace_have_no_leading_dot() {
+        let report = check(&parse(&[(
+            "a.xml",
+            "<mapper>\n<sql id=\"unused\">a</sql>\n\
+             <select id=\"q\">select 1</select>\n<select id=\"q\">select 2</select>\n</mapper>",
+        )]));
+        assert_eq!(report.unused[0].id, "unused");
+        assert_eq!(
+            duplicates(&report),
+            [("statement", "q", vec!["a.xml:3", "a.xml:4"])]
+        );
+    }
+
+    #[test]
+    fn unused_and_missing_follow_nested_fragments() {
+        let report = check(&parse(&[(
+            "a.xml",
+            "<mapper namespace=\"m\">\n\
+             <sql id=\"outer\">a <include refid=\"inner\"/></sql>\n\
+             <sql id=\"inner\">b <include refid=\"gone\"/></sql>\n\
+             <sql id=\"alone\">c</sql>\n\
+             <select id=\"q\">select <include refid=\"outer\"/> <include refid=\"nope\"/></select>\n\
+             </mapper>",
+        )]));
+        let unused: Vec<_> = report.unused.iter().map(|f| f.id.as_str()).collect();
+        assert_eq!(unused, ["m.alone"]);
+        let missing: Vec<_> = report
+            .missing
+            .iter()
+            .map(|m| (m.line, m.owner.as_str(), m.refid.as_str()))
+            .collect();
+        assert_eq!(missing, [(5, "m.q", "nope"), (3, "m.inner", "gone")]);
+        assert!(report.duplicates.is_empty());
+    }
+}
@@ -0,0 +1,2 @@
+/// 未引用的片段、找不到的引用与重复的语句 `id`
+pub mod mapper_hygiene;
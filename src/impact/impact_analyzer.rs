@@ -0,0 +1,319 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    inventory::table_inventory::{self, StatementUsage},
+    save::csv_writer,
+    sql::lexer::{self, TemplateSyntax, TokenKind},
+    xbatis::def::DialectType,
+};
+use log::warn;
+use serde::Serialize;
+
+/// 受影响的语句
+#[derive(Serialize)]
+pub struct Impact {
+    pub file: String,
+    pub line: u64,
+    pub key: String,
+    pub kind: String,
+    /// `read`/`write`/`read-write`，语句无法解析时为 `unknown`
+    pub access: String,
+    /// 语句自身未引用、只经由 `include` 引用时为所经的片段
+    pub via: Vec<String>,
+}
+
+/// 影响分析报告
+#[derive(Serialize)]
+pub struct ImpactReport {
+    pub table: String,
+    pub column: Option<String>,
+    pub total: usize,
+    pub impacts: Vec<Impact>,
+}
+
+/// 找出读写指定表（及字段）的语句。表名忽略大小写与 `schema`；
+/// 无法解析的语句退而按词匹配
+pub fn analyze(
+    sqls: &[ExtractedSql],
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+    table: &str,
+    column: Option<&str>,
+) -> ImpactReport {
+    let table = table.rsplit('.').next().unwrap_or(table).to_lowercase();
+    let column = column.map(|c| c.to_lowercase());
+    let mut report = ImpactReport {
+        table: table.clone(),
+        column: column.clone(),
+        total: sqls.len(),
+        impacts: Vec::new(),
+    };
+    for stat in sqls {
+        let usage = table_inventory::usage(stat, dialect_type);
+        let access = if usage.error.is_some() {
+            let mentioned = mentions(&stat.sql, &table, dialect_type, TemplateSyntax::MyBatis)
+                && column
+                    .as_ref()
+                    .is_none_or(|c| mentions(&stat.sql, c, dialect_type, TemplateSyntax::MyBatis));
+            mentioned.then_some("unknown")
+        } else {
+            access_of(&usage, &table, column.as_deref())
+        };
+        let Some(access) = access else {
+            continue;
+        };
+        // 以最具体的名字判断引用来自语句自身还是片段
+        let name = column.as_ref().unwrap_or(&table);
+        let via = if mentions(&stat.own_sql, name, dialect_type, syntax) {
+            Vec::new()
+        } else {
+            stat.fragments
+                .iter()
+                .filter(|f| mentions(&f.sql, name, dialect_type, syntax))
+                .map(|f| f.id.clone())
+                .collect()
+        };
+        report.impacts.push(Impact {
+            file: stat.file.clone(),
+            line: stat.line,
+            key: stat.key(),
+            kind: usage.kind,
+            access: String::from(access),
+            via,
+        });
+    }
+    report
+}
+
+/// 语句对表的访问方式；指定字段时，还须引用归属该表的该字段，
+/// 或以该表的 `*`、不带字段列表的 `INSERT` 涉及全部字段
+fn access_of(usage: &StatementUsage, table: &str, column: Option<&str>) -> Option<&'static str> {
+    let read = usage.reads.iter().any(|t| t == table);
+    let written = usage.writes.iter().any(|t| t == table);
+    let touched = match column {
+        None => read || written,
+        Some(column) => {
+            let prefix = format!("{table}.");
+            let own_columns: Vec<&str> = usage
+                .columns
+                .iter()
+                .filter_map(|c| c.strip_prefix(&prefix))
+                .collect();
+            (read || written)
+                && (own_columns.contains(&column)
+                    || usage.wildcards.iter().any(|t| t == table)
+                    || (written && usage.kind == "insert" && own_columns.is_empty()))
+        }
+    };
+    match (touched, read, written) {
+        (false, _, _) => None,
+        (true, true, true) => Some("read-write"),
+        (true, false, true) => Some("write"),
+        _ => Some("read"),
+    }
+}
+
+/// 是否有 `SELECT *`、`t.*`
+pub fn has_wildcard(sql: &str, dialect_type: &DialectType) -> bool {
+    let tokens = lexer::tokenize(sql, dialect_type, TemplateSyntax::MyBatis);
+    tokens.iter().enumerate().any(|(i, t)| {
+        t.is_symbol("*")
+            && i > 0
+            && (tokens[i - 1].is_word("SELECT")
+                || tokens[i - 1].is_word("DISTINCT")
+                || tokens[i - 1].is_symbol(",")
+                || tokens[i - 1].is_symbol("."))
+    })
+}
+
+/// 文本中是否出现该名字，加引号的标识符去掉引号后比较
+fn mentions(sql: &str, name: &str, dialect_type: &DialectType, syntax: TemplateSyntax) -> bool {
+    lexer::tokenize(sql, dialect_type, syntax)
+        .iter()
+        .any(|t| match t.kind {
+            TokenKind::Word => t.text.eq_ignore_ascii_case(name),
+            TokenKind::QuotedIdent => t.text[1..t.text.len() - 1].eq_ignore_ascii_case(name),
+            _ => false,
+        })
+}
+
+fn target(report: &ImpactReport) -> String {
+    match &report.column {
+        Some(column) => format!("{}.{}", report.table, column),
+        None => report.table.clone(),
+    }
+}
+
+/// 文本格式输出
+pub fn print_text(report: &ImpactReport) {
+    for impact in report.impacts.iter() {
+        let reach = if impact.via.is_empty() {
+            String::new()
+        } else {
+            format!(" via {}", impact.via.join(", "))
+        };
+        println!(
+            "{}:{}: {} [{}]{}",
+            impact.file, impact.line, impact.key, impact.access, reach
+        );
+    }
+    println!(
+        "{}: {} of {} statements impacted",
+        target(report),
+        report.impacts.len(),
+        report.total
+    );
+}
+
+/// `csv` 格式输出
+pub fn print_csv(report: &ImpactReport) {
+    csv_writer::print_row(&["file", "line", "statement", "kind", "access", "via"]);
+    for impact in report.impacts.iter() {
+        csv_writer::print_row(&[
+            impact.file.as_str(),
+            &impact.line.to_string(),
+            &impact.key,
+            &impact.kind,
+            &impact.access,
+            &impact.via.join(" "),
+        ]);
+    }
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &ImpactReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize impact report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::sql_extractor::Fragment;
+
+    fn stat(id: &str, sql: &str, own_sql: &str, fragments: &[(&str, &str)]) -> ExtractedSql {
+        ExtractedSql {
+            file: String::from("order.xml"),
+            namespace: String::from("order"),
+            id: String::from(id),
+            sql: String::from(sql),
+            template: String::from(sql),
+            own_sql: String::from(own_sql),
+            fragments: fragments
+                .iter()
+                .map(|(id, sql)| Fragment {
+                    id: String::from(*id),
+                    sql: String::from(*sql),
+                    depth: 1,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn impacted(sqls: &[ExtractedSql], table: &str, column: Option<&str>) -> Vec<(String, String)> {
+        analyze(
+            sqls,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            table,
+            column,
+        )
+        .impacts
+        .into_iter()
+        .map(|i| (i.key, i.access))
+        .collect()
+    }
+
+    #[test]
+    fn direct_reads_and_writes() {
+        let sqls = [
+            stat("read", "SELECT o.status FROM orders o", "", &[]),
+            stat(
+                "copy",
+                "UPDATE orders SET status = (SELECT MAX(status) FROM orders) WHERE id = 1",
+                "",
+                &[],
+            ),
+            stat("other", "SELECT status FROM customers", "", &[]),
+        ];
+        assert_eq!(
+            impacted(&sqls, "db.ORDERS", None),
+            [
+                (String::from("order.read"), String::from("read")),
+                (String::from("order.copy"), String::from("read-write"))
+            ]
+        );
+        // 同名字段属于其他表时不算
+        let keys: Vec<String> = impacted(&sqls, "orders", Some("status"))
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["order.read", "order.copy"]);
+        let sqls = [stat(
+            "join",
+            "SELECT c.status FROM orders o JOIN customers c ON c.id = o.customer_id",
+            "",
+            &[],
+        )];
+        assert!(impacted(&sqls, "orders", Some("status")).is_empty());
+    }
+
+    #[test]
+    fn include_only_references_are_via_fragments() {
+        let sqls = [stat(
+            "list",
+            "SELECT id, status FROM orders WHERE deleted = 0",
+            "SELECT id, <include refid=\"cols\"/> FROM orders WHERE deleted = 0",
+            &[("cols", "status")],
+        )];
+        let report = analyze(
+            &sqls,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            "orders",
+            Some("status"),
+        );
+        assert_eq!(report.impacts[0].via, ["cols"]);
+        let report = analyze(
+            &sqls,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            "orders",
+            Some("deleted"),
+        );
+        assert!(report.impacts[0].via.is_empty());
+    }
+
+    #[test]
+    fn wildcards_count_only_for_projected_tables() {
+        let sqls = [
+            stat("all", "SELECT * FROM orders", "", &[]),
+            stat(
+                "qualified",
+                "SELECT o.* FROM orders o JOIN customers c ON c.id = o.customer_id",
+                "",
+                &[],
+            ),
+            stat(
+                "other",
+                "SELECT c.* FROM orders o JOIN customers c ON c.id = o.customer_id",
+                "",
+                &[],
+            ),
+            stat(
+                "nested",
+                "SELECT * FROM customers WHERE id IN (SELECT customer_id FROM orders)",
+                "",
+                &[],
+            ),
+        ];
+        let keys: Vec<String> = impacted(&sqls, "orders", Some("status"))
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        assert_eq!(keys, ["order.all", "order.qualified"]);
+    }
+}
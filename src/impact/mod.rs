@@ -0,0 +1,2 @@
+/// 表、字段变更的影响分析
+pub mod impact_analyzer;
@@ -0,0 +1,2 @@
+/// 表与字段的使用清单
+pub mod table_inventory;
@@ -0,0 +1,592 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql, save::csv_writer, validate::sql_validator,
+    xbatis::def::DialectType,
+};
+use log::warn;
+use serde::Serialize;
+use sqlparser::ast::{
+    AssignmentTarget, BinaryOperator, Expr, FromTable, Ident, ObjectName, Query, SelectItem,
+    SelectItemQualifiedWildcardKind, SetExpr, Statement, TableFactor, TableObject, Visit, Visitor,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    ops::ControlFlow,
+};
+
+/// 单条语句用到的表与字段，表名、字段名均转为小写，去掉 `schema`
+#[derive(Serialize)]
+pub struct StatementUsage {
+    pub file: String,
+    pub key: String,
+    /// `select`/`insert`/`update`/`delete`/`merge`/`other`，无法解析时取 `xml` 中的类型
+    pub kind: String,
+    pub reads: Vec<String>,
+    pub writes: Vec<String>,
+    /// `table.column`，无法确定所属表时只有字段名
+    pub columns: Vec<String>,
+    /// 两侧均为字段的等值条件，`a.id = b.a_id`
+    pub joins: Vec<String>,
+    /// 以 `*` 或 `t.*` 取出全部字段的表
+    pub wildcards: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// 单张表被哪些语句读写
+#[derive(Serialize, Default)]
+pub struct TableUsage {
+    pub read_by: BTreeSet<String>,
+    pub written_by: BTreeSet<String>,
+    pub columns: BTreeSet<String>,
+}
+
+/// 清单报告
+#[derive(Serialize, Default)]
+pub struct InventoryReport {
+    pub total: usize,
+    pub failed: usize,
+    pub tables: BTreeMap<String, TableUsage>,
+    pub statements: Vec<StatementUsage>,
+}
+
+/// 解析每条语句，汇总为表到语句的交叉引用
+pub fn inventory(sqls: &[ExtractedSql], dialect_type: &DialectType) -> InventoryReport {
+    let mut report = InventoryReport {
+        total: sqls.len(),
+        ..Default::default()
+    };
+    for stat in sqls {
+        let usage = usage(stat, dialect_type);
+        if usage.error.is_some() {
+            report.failed += 1;
+        }
+        let key = usage.key.clone();
+        for table in usage.reads.iter() {
+            let entry = report.tables.entry(table.clone()).or_default();
+            entry.read_by.insert(key.clone());
+        }
+        for table in usage.writes.iter() {
+            let entry = report.tables.entry(table.clone()).or_default();
+            entry.written_by.insert(key.clone());
+        }
+        for column in usage.columns.iter() {
+            if let Some((table, column)) = column.split_once('.') {
+                if let Some(entry) = report.tables.get_mut(table) {
+                    entry.columns.insert(column.to_string());
+                }
+            }
+        }
+        report.statements.push(usage);
+    }
+    report
+}
+
+/// 单条语句用到的表与字段，无法解析时记录错误
+pub fn usage(stat: &ExtractedSql, dialect_type: &DialectType) -> StatementUsage {
+    let mut usage = StatementUsage {
+        file: stat.file.clone(),
+        key: stat.key(),
+        kind: stat.kind.clone(),
+        reads: Vec::new(),
+        writes: Vec::new(),
+        columns: Vec::new(),
+        joins: Vec::new(),
+        wildcards: Vec::new(),
+        error: None,
+    };
+    let statements = match sql_validator::parse(&stat.template, dialect_type) {
+        Ok(statements) => statements,
+        Err(e) => {
+            usage.error = Some(e.to_string());
+            return usage;
+        }
+    };
+    let mut reads = BTreeSet::new();
+    let mut writes = BTreeSet::new();
+    let mut columns = BTreeSet::new();
+    let mut joins = BTreeSet::new();
+    let mut wildcards = BTreeSet::new();
+    for statement in statements.iter() {
+        let mut collector = Collector::new();
+        let _ = statement.visit(&mut collector);
+        let (kind, written, written_columns) = target_of(statement, &collector);
+        usage.kind = String::from(kind);
+        // 语句本身的写入目标不算读取，子查询、`FROM`、`USING` 中的同一张表仍然算
+        for (scope, table) in collector.relations.iter() {
+            if *scope != 0 || !written.contains(table) {
+                reads.insert(table.clone());
+            }
+        }
+        for (scope, column) in collector.columns.iter() {
+            columns.insert(collector.qualify(column, *scope));
+        }
+        for column in written_columns.iter() {
+            columns.insert(collector.qualify(column, 0));
+        }
+        for (scope, left, right) in collector.joins.iter() {
+            let (left, right) = (
+                collector.qualify(left, *scope),
+                collector.qualify(right, *scope),
+            );
+            if left != right {
+                joins.insert(format!("{left} = {right}"));
+            }
+        }
+        for (scope, qualifier) in collector.wildcards.iter() {
+            match qualifier {
+                Some(qualifier) => wildcards.extend(collector.resolve(qualifier, *scope)),
+                None => wildcards.extend(collector.scopes[*scope].tables.iter().cloned()),
+            }
+        }
+        writes.extend(written);
+    }
+    usage.reads = reads.into_iter().collect();
+    usage.writes = writes.into_iter().collect();
+    usage.columns = columns.into_iter().collect();
+    usage.joins = joins.into_iter().collect();
+    usage.wildcards = wildcards.into_iter().collect();
+    usage
+}
+
+/// 单层查询的作用域
+#[derive(Default)]
+struct Scope {
+    /// 外层作用域，语句本身的外层为自身
+    parent: usize,
+    /// 直接引用的表
+    tables: BTreeSet<String>,
+    /// 直接引用的 `WITH` 子查询与派生表个数
+    others: usize,
+    /// 别名到表名，`WITH` 子查询与派生表的别名为 `None`
+    aliases: HashMap<String, Option<String>>,
+    /// 本层定义的 `WITH` 子查询
+    ctes: BTreeSet<String>,
+}
+
+/// 遍历语句，收集表、别名、字段引用与等值条件。
+/// 每个查询为一层作用域，`0` 为语句本身，别名与 `WITH` 子查询在本层及内层可见；
+/// 字段先于 `FROM` 被访问，遍历结束后再按作用域归属
+struct Collector {
+    /// 引用的表及所在的作用域
+    relations: Vec<(usize, String)>,
+    scopes: Vec<Scope>,
+    /// 当前所在的作用域
+    stack: Vec<usize>,
+    columns: Vec<(usize, Vec<String>)>,
+    joins: Vec<(usize, Vec<String>, Vec<String>)>,
+    /// 投影中的 `*`，`t.*` 带有限定名
+    wildcards: Vec<(usize, Option<String>)>,
+}
+
+impl Collector {
+    fn new() -> Self {
+        Collector {
+            relations: Vec::new(),
+            scopes: vec![Scope::default()],
+            stack: vec![0],
+            columns: Vec::new(),
+            joins: Vec::new(),
+            wildcards: Vec::new(),
+        }
+    }
+
+    fn scope(&self) -> usize {
+        self.stack.last().copied().unwrap_or(0)
+    }
+
+    /// 由内向外依次经过的作用域
+    fn chain(&self, scope: usize) -> impl Iterator<Item = &Scope> {
+        let mut next = Some(scope);
+        std::iter::from_fn(move || {
+            let current = next?;
+            let scope = &self.scopes[current];
+            next = (current != 0).then_some(scope.parent);
+            Some(scope)
+        })
+    }
+
+    fn is_cte(&self, name: &str, scope: usize) -> bool {
+        self.chain(scope).any(|s| s.ctes.contains(name))
+    }
+
+    /// 别名或表名对应的表，`WITH` 子查询与派生表为 `None`
+    fn resolve(&self, qualifier: &str, scope: usize) -> Option<String> {
+        match self.chain(scope).find_map(|s| s.aliases.get(qualifier)) {
+            Some(table) => table.clone(),
+            None if self.is_cte(qualifier, scope) => None,
+            None => Some(String::from(qualifier)),
+        }
+    }
+
+    /// 字段所属的表：限定名按本层及外层的别名解析；未限定时，所在作用域只有一张表则归属该表
+    fn qualify(&self, path: &[String], scope: usize) -> String {
+        let column = path.last().cloned().unwrap_or_default();
+        let tables = &self.scopes[scope].tables;
+        let table = if path.len() >= 2 {
+            self.resolve(&path[path.len() - 2], scope)
+        } else if tables.len() == 1 && self.scopes[scope].others == 0 {
+            tables.iter().next().cloned()
+        } else {
+            None
+        };
+        match table {
+            Some(table) => format!("{table}.{column}"),
+            None => column,
+        }
+    }
+}
+
+impl Visitor for Collector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        let ctes = query
+            .with
+            .iter()
+            .flat_map(|with| with.cte_tables.iter())
+            .map(|cte| lower(&cte.alias.name))
+            .collect();
+        self.scopes.push(Scope {
+            parent: self.scope(),
+            ctes,
+            ..Default::default()
+        });
+        self.stack.push(self.scopes.len() - 1);
+        let scope = self.scope();
+        let mut wildcards = Vec::new();
+        projected_wildcards(&query.body, &mut wildcards);
+        self.wildcards
+            .extend(wildcards.into_iter().map(|w| (scope, w)));
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.stack.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        let table = table_name(relation);
+        let scope = self.scope();
+        if relation.0.len() == 1 && self.is_cte(&table, scope) {
+            self.scopes[scope].others += 1;
+        } else {
+            self.scopes[scope].tables.insert(table.clone());
+            self.relations.push((scope, table));
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        let scope = self.scope();
+        match table_factor {
+            TableFactor::Table {
+                name,
+                alias: Some(alias),
+                ..
+            } => {
+                let table = table_name(name);
+                let table = (name.0.len() > 1 || !self.is_cte(&table, scope)).then_some(table);
+                self.scopes[scope].aliases.insert(lower(&alias.name), table);
+            }
+            TableFactor::Derived { alias, .. } => {
+                self.scopes[scope].others += 1;
+                if let Some(alias) = alias {
+                    self.scopes[scope].aliases.insert(lower(&alias.name), None);
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        let scope = self.scope();
+        if let Some(path) = column_path(expr) {
+            self.columns.push((scope, path));
+        } else if let Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } = expr
+        {
+            if let (Some(left), Some(right)) = (column_path(left), column_path(right)) {
+                self.joins.push((scope, left, right));
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// 语句类型、写入的表，以及 `INSERT` 字段列表、`UPDATE` 赋值目标等不在表达式中的字段
+fn target_of(
+    statement: &Statement,
+    collector: &Collector,
+) -> (&'static str, BTreeSet<String>, Vec<Vec<String>>) {
+    let resolve = |name: &ObjectName| {
+        let table = table_name(name);
+        collector.resolve(&table, 0).unwrap_or(table)
+    };
+    match statement {
+        Statement::Query(_) => ("select", BTreeSet::new(), Vec::new()),
+        Statement::Insert(insert) => {
+            let table = match &insert.table {
+                TableObject::TableName(name) => resolve(name),
+                _ => return ("insert", BTreeSet::new(), Vec::new()),
+            };
+            let columns = insert
+                .columns
+                .iter()
+                .map(|c| vec![table.clone(), lower(c)])
+                .collect();
+            ("insert", BTreeSet::from([table]), columns)
+        }
+        Statement::Update {
+            table, assignments, ..
+        } => {
+            let written: BTreeSet<String> = relation_name(&table.relation).into_iter().collect();
+            let columns = assignments
+                .iter()
+                .flat_map(|a| match &a.target {
+                    AssignmentTarget::ColumnName(name) => vec![name_path(name)],
+                    AssignmentTarget::Tuple(names) => names.iter().map(name_path).collect(),
+                })
+                .collect();
+            ("update", written, columns)
+        }
+        Statement::Delete(delete) => {
+            let written = if delete.tables.is_empty() {
+                match &delete.from {
+                    FromTable::WithFromKeyword(tables) | FromTable::WithoutKeyword(tables) => {
+                        tables
+                            .iter()
+                            .filter_map(|t| relation_name(&t.relation))
+                            .collect()
+                    }
+                }
+            } else {
+                delete.tables.iter().map(resolve).collect()
+            };
+            ("delete", written, Vec::new())
+        }
+        Statement::Merge { table, .. } => (
+            "merge",
+            relation_name(table).into_iter().collect(),
+            Vec::new(),
+        ),
+        _ => ("other", BTreeSet::new(), Vec::new()),
+    }
+}
+
+/// 本层查询投影中的 `*` 与 `t.*` 的限定名，`UNION` 等各分支同属一层
+fn projected_wildcards(body: &SetExpr, wildcards: &mut Vec<Option<String>>) {
+    match body {
+        SetExpr::Select(select) => {
+            for item in select.projection.iter() {
+                match item {
+                    SelectItem::Wildcard(_) => wildcards.push(None),
+                    SelectItem::QualifiedWildcard(
+                        SelectItemQualifiedWildcardKind::ObjectName(name),
+                        _,
+                    ) => wildcards.push(Some(table_name(name))),
+                    _ => {}
+                }
+            }
+        }
+        SetExpr::SetOperation { left, right, .. } => {
+            projected_wildcards(left, wildcards);
+            projected_wildcards(right, wildcards);
+        }
+        _ => {}
+    }
+}
+
+fn column_path(expr: &Expr) -> Option<Vec<String>> {
+    match expr {
+        Expr::Identifier(ident) => Some(vec![lower(ident)]),
+        Expr::CompoundIdentifier(idents) => Some(idents.iter().map(lower).collect()),
+        _ => None,
+    }
+}
+
+fn relation_name(relation: &TableFactor) -> Option<String> {
+    match relation {
+        TableFactor::Table { name, .. } => Some(table_name(name)),
+        _ => None,
+    }
+}
+
+/// 表名取最后一段，`schema` 不计
+fn table_name(name: &ObjectName) -> String {
+    name.0
+        .last()
+        .and_then(|part| part.as_ident())
+        .map(lower)
+        .unwrap_or_default()
+}
+
+fn name_path(name: &ObjectName) -> Vec<String> {
+    name.0
+        .iter()
+        .filter_map(|part| part.as_ident())
+        .map(lower)
+        .collect()
+}
+
+fn lower(ident: &Ident) -> String {
+    ident.value.to_lowercase()
+}
+
+/// 文本格式输出，按表列出读写的语句
+pub fn print_text(report: &InventoryReport) {
+    for (table, usage) in report.tables.iter() {
+        println!(
+            "{table}: read by {}, written by {}",
+            usage.read_by.len(),
+            usage.written_by.len()
+        );
+        print_list("columns", &usage.columns);
+        print_list("read", &usage.read_by);
+        print_list("write", &usage.written_by);
+    }
+    for stat in report.statements.iter() {
+        if let Some(error) = &stat.error {
+            println!("{}: {} error: {}", stat.file, stat.key, error);
+        }
+    }
+    println!(
+        "inventoried: {}, tables: {}, failed: {}",
+        report.total,
+        report.tables.len(),
+        report.failed
+    );
+}
+
+fn print_list(label: &str, values: &BTreeSet<String>) {
+    if !values.is_empty() {
+        let values: Vec<&str> = values.iter().map(|v| v.as_str()).collect();
+        println!("    {label}: {}", values.join(", "));
+    }
+}
+
+/// `csv` 格式输出，每行为一张表与一条语句的对应，按表排序
+pub fn print_csv(report: &InventoryReport) {
+    let mut rows: Vec<[String; 7]> = Vec::new();
+    for stat in report.statements.iter() {
+        let tables: BTreeSet<&String> = stat.reads.iter().chain(stat.writes.iter()).collect();
+        for table in tables {
+            let prefix = format!("{table}.");
+            let columns: Vec<&str> = stat
+                .columns
+                .iter()
+                .filter_map(|c| c.strip_prefix(&prefix))
+                .collect();
+            let joins: Vec<&str> = stat
+                .joins
+                .iter()
+                .filter(|j| j.split(" = ").any(|side| side.starts_with(&prefix)))
+                .map(|j| j.as_str())
+                .collect();
+            rows.push([
+                table.clone(),
+                String::from(access(stat, table)),
+                stat.key.clone(),
+                stat.file.clone(),
+                stat.kind.clone(),
+                columns.join(" "),
+                joins.join("; "),
+            ]);
+        }
+    }
+    rows.sort();
+    csv_writer::print_row(&[
+        "table",
+        "access",
+        "statement",
+        "file",
+        "kind",
+        "columns",
+        "joins",
+    ]);
+    for row in rows.iter() {
+        csv_writer::print_row(row);
+    }
+}
+
+/// 语句对表的读写方式
+fn access(stat: &StatementUsage, table: &String) -> &'static str {
+    match (stat.reads.contains(table), stat.writes.contains(table)) {
+        (true, true) => "read-write",
+        (false, true) => "write",
+        _ => "read",
+    }
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &InventoryReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize inventory report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage_of(sql: &str) -> StatementUsage {
+        let stat = ExtractedSql {
+            file: String::from("order.xml"),
+            namespace: String::from("order"),
+            id: String::from("a"),
+            template: String::from(sql),
+            ..Default::default()
+        };
+        usage(&stat, &DialectType::MySQL)
+    }
+
+    #[test]
+    fn self_reads_are_read_write() {
+        let usage = usage_of(
+            "UPDATE orders SET total = (SELECT MAX(total) FROM orders WHERE id = 1) WHERE id = #{id}",
+        );
+        assert_eq!(usage.kind, "update");
+        assert_eq!(usage.reads, ["orders"]);
+        assert_eq!(usage.writes, ["orders"]);
+        assert_eq!(access(&usage, &String::from("orders")), "read-write");
+        let usage = usage_of("INSERT INTO archive (id) SELECT id FROM archive WHERE id > 1");
+        assert_eq!(usage.reads, ["archive"]);
+        assert_eq!(usage.writes, ["archive"]);
+        let usage = usage_of("DELETE FROM orders WHERE customer_id IN (SELECT id FROM customers)");
+        assert_eq!(usage.reads, ["customers"]);
+        assert_eq!(usage.writes, ["orders"]);
+        assert_eq!(access(&usage, &String::from("orders")), "write");
+    }
+
+    #[test]
+    fn aliases_belong_to_their_query() {
+        let usage = usage_of(
+            "SELECT a.id FROM orders a WHERE EXISTS (SELECT 1 FROM customers a WHERE a.vip = 1)",
+        );
+        assert_eq!(usage.columns, ["customers.vip", "orders.id"]);
+        // 内层可以引用外层的别名
+        let usage = usage_of(
+            "SELECT o.id FROM orders o WHERE EXISTS (SELECT 1 FROM items i WHERE i.order_id = o.id)",
+        );
+        assert_eq!(usage.joins, ["items.order_id = orders.id"]);
+    }
+
+    #[test]
+    fn cte_names_are_not_tables() {
+        let usage = usage_of(
+            "WITH recent AS (SELECT id, customer_id FROM orders) SELECT r.id, c.name FROM recent r JOIN customers c ON c.id = r.customer_id",
+        );
+        assert_eq!(usage.reads, ["customers", "orders"]);
+        assert!(usage.columns.contains(&String::from("customers.name")));
+        assert!(usage.columns.contains(&String::from("orders.customer_id")));
+        assert!(usage.columns.iter().all(|c| !c.starts_with("recent.")));
+        // 只引用 `WITH` 子查询时，未限定的字段不归属其他表
+        let usage = usage_of("WITH t AS (SELECT 1 AS x) SELECT x, y FROM t, orders");
+        assert!(usage.columns.contains(&String::from("x")));
+        assert_eq!(usage.reads, ["orders"]);
+    }
+}
@@ -0,0 +1,495 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    sql::lexer::{self, TemplateSyntax, Token, TokenKind},
+    xbatis::def::{DialectType, DynamicNode, DynamicPart},
+};
+
+/// 最多检查的条件组合数，超出时只检查靠前的组合
+const MAX_COMBINATIONS: usize = 4096;
+
+/// 展开片段的最大层数，超出时按原文拼接，避免循环引用
+const MAX_INCLUDE_DEPTH: usize = 10;
+
+/// 条件中不是字段的词：运算符、常量、无参数的日期时间函数与类型字面量的前缀
+const NON_COLUMNS: [&str; 21] = [
+    "NOT",
+    "NULL",
+    "TRUE",
+    "FALSE",
+    "UNKNOWN",
+    "IS",
+    "AND",
+    "OR",
+    "LIKE",
+    "IN",
+    "BETWEEN",
+    "ESCAPE",
+    "SYSDATE",
+    "SYSTIMESTAMP",
+    "CURRENT_DATE",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "LOCALTIMESTAMP",
+    "DATE",
+    "TIMESTAMP",
+    "INTERVAL",
+];
+
+/// 条件组合下丢失限制条件的语句
+pub struct LostWhere {
+    /// 该组合下各条件的取值，如 `id != null is false`
+    pub conditions: Vec<String>,
+    /// 该组合下得到的 `sql`，未规整
+    pub sql: String,
+}
+
+/// 逐个条件组合展开动态元素，找出不再有限制条件的组合。
+/// `<if>` 与 `iBATIS` 的 `<isXxx>` 取成立或不成立，`<choose>` 取每个分支，循环取空或非空；
+/// `include` 的片段中的动态元素一同参与组合；从全部不成立开始检查，返回第一个丢失限制条件的组合
+pub fn lost_where(
+    stat: &ExtractedSql,
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+) -> Option<LostWhere> {
+    if stat.dynamic.tag.is_empty() {
+        return None;
+    }
+    let dynamic = inline_fragments(&stat.dynamic, stat, 0);
+    let mut points = Vec::new();
+    choice_points(&dynamic, &mut points);
+    let arities: Vec<usize> = points.iter().map(|p| arity(p)).collect();
+    let mut values = vec![0; points.len()];
+    for _ in 0..MAX_COMBINATIONS {
+        let mut renderer = Renderer {
+            values: &values,
+            next: 0,
+            stat,
+            conditions: Vec::new(),
+        };
+        let sql = renderer.children(&dynamic);
+        if !restricted(&lexer::tokenize(&sql, dialect_type, syntax)) {
+            return Some(LostWhere {
+                conditions: renderer.conditions,
+                sql: sql.split_whitespace().collect::<Vec<&str>>().join(" "),
+            });
+        }
+        if !advance(&mut values, &arities) {
+            break;
+        }
+    }
+    None
+}
+
+/// 以片段的动态元素结构代替 `include`，找不到片段或没有结构时保留 `include`，展开时按原文拼接
+fn inline_fragments(node: &DynamicNode, stat: &ExtractedSql, depth: usize) -> DynamicNode {
+    let mut inlined = DynamicNode {
+        tag: node.tag.clone(),
+        attrs: node.attrs.clone(),
+        children: Vec::new(),
+    };
+    for part in node.children.iter() {
+        let part = match part {
+            DynamicPart::Element(e) if e.tag == "include" => {
+                let fragment = e.attr("refid").and_then(|refid| stat.fragment(refid));
+                match fragment {
+                    Some(f) if !f.dynamic.tag.is_empty() && depth < MAX_INCLUDE_DEPTH => {
+                        DynamicPart::Element(inline_fragments(&f.dynamic, stat, depth + 1))
+                    }
+                    _ => part.clone(),
+                }
+            }
+            DynamicPart::Element(e) => DynamicPart::Element(inline_fragments(e, stat, depth)),
+            DynamicPart::Text(_) => part.clone(),
+        };
+        inlined.children.push(part);
+    }
+    inlined
+}
+
+/// 下一个组合，已是最后一个时返回 `false`
+fn advance(values: &mut [usize], arities: &[usize]) -> bool {
+    for (value, arity) in values.iter_mut().zip(arities.iter()) {
+        *value += 1;
+        if *value < *arity {
+            return true;
+        }
+        *value = 0;
+    }
+    false
+}
+
+/// 先序收集取值不止一种的元素，与展开时的编号一致
+fn choice_points<'a>(node: &'a DynamicNode, points: &mut Vec<&'a DynamicNode>) {
+    for part in node.children.iter() {
+        if let DynamicPart::Element(e) = part {
+            if arity(e) > 1 {
+                points.push(e);
+            }
+            choice_points(e, points);
+        }
+    }
+}
+
+fn count(node: &DynamicNode) -> usize {
+    let mut points = Vec::new();
+    choice_points(node, &mut points);
+    points.len()
+}
+
+/// 取值的种数，`<choose>` 为各个 `<when>` 加上 `<otherwise>`（没有时为都不匹配）
+fn arity(node: &DynamicNode) -> usize {
+    match node.tag.as_str() {
+        "choose" => elements(node).filter(|e| e.tag == "when").count() + 1,
+        "when" | "otherwise" => 1,
+        _ if node.is_conditional() => 2,
+        _ => 1,
+    }
+}
+
+fn elements(node: &DynamicNode) -> impl Iterator<Item = &DynamicNode> {
+    node.children.iter().filter_map(|part| match part {
+        DynamicPart::Element(e) => Some(e),
+        DynamicPart::Text(_) => None,
+    })
+}
+
+/// 按一个条件组合展开动态元素
+struct Renderer<'a> {
+    values: &'a [usize],
+    /// 下一个取值元素的编号
+    next: usize,
+    stat: &'a ExtractedSql,
+    conditions: Vec<String>,
+}
+
+impl Renderer<'_> {
+    fn children(&mut self, node: &DynamicNode) -> String {
+        let mut sql = String::new();
+        for part in node.children.iter() {
+            match part {
+                DynamicPart::Text(text) => sql += text,
+                DynamicPart::Element(e) => {
+                    sql += " ";
+                    sql += &self.element(e);
+                    sql += " ";
+                }
+            }
+        }
+        sql
+    }
+
+    fn element(&mut self, node: &DynamicNode) -> String {
+        match node.tag.as_str() {
+            "include" => {
+                let fragment = node
+                    .attr("refid")
+                    .and_then(|refid| self.stat.fragment(refid));
+                // 找不到的片段视为有限制条件，避免误报
+                fragment.map_or(String::from("__include__"), |f| f.sql.clone())
+            }
+            "bind" => String::new(),
+            "where" => wrap(&self.children(node), "WHERE", &["AND", "OR"], &[]),
+            "set" => wrap(&self.children(node), "SET", &[], &[","]),
+            "trim" => {
+                let prefix_overrides = overrides(node.attr("prefixOverrides"));
+                let suffix_overrides = overrides(node.attr("suffixOverrides"));
+                let inner = wrap(
+                    &self.children(node),
+                    node.attr("prefix").unwrap_or_default(),
+                    &prefix_overrides,
+                    &suffix_overrides,
+                );
+                if inner.is_empty() {
+                    inner
+                } else {
+                    format!("{inner} {}", node.attr("suffix").unwrap_or_default())
+                }
+            }
+            // `iBATIS` 去掉第一个子元素的 `prepend`
+            "dynamic" => wrap(
+                &self.children(node),
+                node.attr("prepend").unwrap_or_default(),
+                &["AND", "OR", ","],
+                &[],
+            ),
+            "choose" => self.choose(node),
+            "when" | "otherwise" => self.children(node),
+            "foreach" | "iterate" => {
+                let value = self.take();
+                let collection = node
+                    .attr("collection")
+                    .or(node.attr("property"))
+                    .unwrap_or("parameter");
+                if value == 0 {
+                    self.next += count(node);
+                    self.conditions.push(format!("{collection} is empty"));
+                    String::new()
+                } else {
+                    self.conditions.push(format!("{collection} is not empty"));
+                    format!(
+                        "{} {} {}",
+                        node.attr("open").unwrap_or_default(),
+                        self.children(node),
+                        node.attr("close").unwrap_or_default()
+                    )
+                }
+            }
+            _ if node.is_conditional() => {
+                let value = self.take();
+                let condition = match node.attr("test") {
+                    Some(test) => String::from(test),
+                    None => format!("{} {}", node.tag, node.attr("property").unwrap_or_default()),
+                };
+                if value == 0 {
+                    self.next += count(node);
+                    self.conditions
+                        .push(format!("{} is false", condition.trim()));
+                    String::new()
+                } else {
+                    self.conditions
+                        .push(format!("{} is true", condition.trim()));
+                    format!(
+                        "{} {}",
+                        node.attr("prepend").unwrap_or_default(),
+                        self.children(node)
+                    )
+                }
+            }
+            _ => self.children(node),
+        }
+    }
+
+    /// 取值 `0` 为 `<otherwise>`，没有时为都不匹配；其余依次为各个 `<when>`
+    fn choose(&mut self, node: &DynamicNode) -> String {
+        let value = self.take();
+        let mut sql = String::new();
+        let mut when = 0;
+        for branch in elements(node) {
+            let chosen = match branch.tag.as_str() {
+                "when" => {
+                    when += 1;
+                    when == value
+                }
+                "otherwise" => value == 0,
+                _ => false,
+            };
+            if chosen {
+                self.conditions.push(match branch.attr("test") {
+                    Some(test) => format!("{} is the first true <when>", test.trim()),
+                    None => String::from("<otherwise> is taken"),
+                });
+                sql = self.children(branch);
+            } else {
+                self.next += count(branch);
+            }
+        }
+        if value == 0 && !elements(node).any(|e| e.tag == "otherwise") {
+            self.conditions.push(String::from("no <when> matches"));
+        }
+        sql
+    }
+
+    fn take(&mut self) -> usize {
+        let value = self.values.get(self.next).copied().unwrap_or(0);
+        self.next += 1;
+        value
+    }
+}
+
+/// `prefixOverrides`/`suffixOverrides`，以 `|` 分隔
+fn overrides(value: Option<&str>) -> Vec<&str> {
+    value
+        .map(|v| {
+            v.split('|')
+                .map(|o| o.trim())
+                .filter(|o| !o.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// 内容非空时去掉首尾多余的词并加上前缀，同 `<where>`、`<trim>`
+fn wrap(inner: &str, prefix: &str, prefix_overrides: &[&str], suffix_overrides: &[&str]) -> String {
+    let mut inner = inner.trim();
+    if inner.is_empty() {
+        return String::new();
+    }
+    for o in prefix_overrides {
+        if inner
+            .get(..o.len())
+            .is_some_and(|head| head.eq_ignore_ascii_case(o))
+        {
+            let rest = &inner[o.len()..];
+            if !o.ends_with(char::is_alphanumeric) || !rest.starts_with(is_word_char) {
+                inner = rest.trim_start();
+                break;
+            }
+        }
+    }
+    for o in suffix_overrides {
+        let tail = inner
+            .len()
+            .checked_sub(o.len())
+            .and_then(|i| inner.get(i..));
+        if tail.is_some_and(|tail| tail.eq_ignore_ascii_case(o)) {
+            inner = inner[..inner.len() - o.len()].trim_end();
+            break;
+        }
+    }
+    format!(" {prefix} {inner} ")
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 括号外的 `WHERE` 是否限制了行：按 `OR` 分开的每一支中，至少有一个 `AND` 条件引用了字段。
+/// `1 = 1`、`#{flag} = 1` 这样只有常量与参数的条件不算
+fn restricted(tokens: &[Token]) -> bool {
+    let tokens: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Hint))
+        .collect();
+    let mut depth = 0;
+    let mut start = None;
+    let mut end = tokens.len();
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_symbol("(") {
+            depth += 1;
+        } else if token.is_symbol(")") {
+            depth -= 1;
+        } else if depth == 0 && start.is_none() && token.is_word("WHERE") {
+            start = Some(i + 1);
+        } else if depth == 0
+            && start.is_some()
+            && ["ORDER", "LIMIT", "RETURNING", "FETCH"]
+                .iter()
+                .any(|w| token.is_word(w))
+        {
+            end = i;
+            break;
+        }
+    }
+    let Some(start) = start else {
+        return false;
+    };
+    let predicate = &tokens[start..end.max(start)];
+    split(predicate, "OR")
+        .iter()
+        .all(|branch| split(branch, "AND").iter().any(|c| references_column(c)))
+}
+
+/// 按括号外的关键字切分
+fn split<'a, 'b>(tokens: &'a [&'b Token], word: &str) -> Vec<&'a [&'b Token]> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_symbol("(") {
+            depth += 1;
+        } else if token.is_symbol(")") {
+            depth -= 1;
+        } else if depth == 0 && token.is_word(word) {
+            parts.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// 是否引用了字段；函数名不算，参数中的字段仍然算
+fn references_column(tokens: &[&Token]) -> bool {
+    tokens.iter().enumerate().any(|(i, t)| match t.kind {
+        TokenKind::QuotedIdent => true,
+        TokenKind::Word => {
+            let call = tokens.get(i + 1).is_some_and(|next| next.is_symbol("("));
+            !call && !NON_COLUMNS.iter().any(|w| t.is_word(w))
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        extract::sql_extractor,
+        xbatis::{mybatis_parser, xbatis_parser::Parser},
+    };
+    use std::collections::HashMap;
+
+    fn lost(xml: &str) -> Vec<Option<Vec<String>>> {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        parser.setup_replace_num(10);
+        let xml = format!("<mapper namespace=\"m\">{xml}</mapper>");
+        let parsed = parser.parse_xml("test.xml", &mut xml.as_bytes());
+        let mut global_inc_map = HashMap::new();
+        let mut global_trees = HashMap::new();
+        sql_extractor::register_sql_parts(&mut global_inc_map, &mut global_trees, &parsed);
+        sql_extractor::resolve_file(&parser, "test.xml", &parsed, &global_inc_map, &global_trees)
+            .iter()
+            .map(|stat| {
+                lost_where(stat, &DialectType::MySQL, TemplateSyntax::MyBatis).map(|l| l.conditions)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn combinations_are_enumerated_in_order() {
+        let arities = [2, 3];
+        let mut values = vec![0, 0];
+        let mut seen = vec![values.clone()];
+        while advance(&mut values, &arities) {
+            seen.push(values.clone());
+        }
+        assert_eq!(seen.len(), 6);
+        assert_eq!(seen[1], [1, 0]);
+        assert_eq!(seen[5], [1, 2]);
+    }
+
+    #[test]
+    fn where_lost_when_conditions_are_false() {
+        let result = lost(
+            r#"<delete id="a">DELETE FROM t <where><if test="id != null">id = #{id}</if></where></delete>
+            <delete id="b">DELETE FROM t WHERE status = 'X' <if test="id != null">AND id = #{id}</if></delete>"#,
+        );
+        assert_eq!(result[0], Some(vec![String::from("id != null is false")]));
+        assert_eq!(result[1], None);
+    }
+
+    #[test]
+    fn later_combinations_are_checked() {
+        let result = lost(
+            r#"<update id="a">UPDATE t SET a = 1 <where><choose><when test="all">1 = 1</when><otherwise>id = #{id}</otherwise></choose></where></update>"#,
+        );
+        assert_eq!(
+            result[0],
+            Some(vec![String::from("all is the first true <when>")])
+        );
+    }
+
+    #[test]
+    fn conditions_inside_fragments_are_enumerated() {
+        let result = lost(
+            r#"<sql id="cond"><where><if test="id != null">id = #{id}</if></where></sql>
+            <sql id="outer"><include refid="cond"/></sql>
+            <delete id="a">DELETE FROM t <include refid="cond"/></delete>
+            <delete id="b">DELETE FROM t <include refid="outer"/></delete>"#,
+        );
+        assert_eq!(result[0], Some(vec![String::from("id != null is false")]));
+        assert_eq!(result[1], Some(vec![String::from("id != null is false")]));
+    }
+
+    #[test]
+    fn constants_and_functions_are_not_columns() {
+        let result = lost(
+            r#"<delete id="a">DELETE FROM t WHERE SYSDATE > DATE '2000-01-01' <if test="a">AND a = #{a}</if></delete>
+            <delete id="b">DELETE FROM t WHERE NOW() IS NOT NULL <if test="a">AND a = #{a}</if></delete>
+            <delete id="c">DELETE FROM t WHERE created &lt; NOW() <if test="a">AND a = #{a}</if></delete>"#,
+        );
+        assert_eq!(result[0], Some(vec![String::from("a is false")]));
+        assert_eq!(result[1], Some(vec![String::from("a is false")]));
+        assert_eq!(result[2], None);
+    }
+}
@@ -0,0 +1,4 @@
+/// 动态元素展开后是否仍有限制条件
+pub mod dynamic_where;
+/// 静态检查规则
+pub mod sql_linter;
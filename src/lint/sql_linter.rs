@@ -0,0 +1,797 @@
+use super::dynamic_where;
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    impact::impact_analyzer,
+    sql::lexer::{self, TemplateSyntax, Token, TokenKind},
+    validate::sql_validator,
+    xbatis::def::{DialectType, DynamicNode, DynamicPart},
+};
+use lazy_static::lazy_static;
+use log::warn;
+use regex::Regex;
+use serde::Serialize;
+use sqlparser::ast::{
+    BinaryOperator, Expr, JoinConstraint, JoinOperator, Query, Select, SetExpr, Statement,
+    TableFactor, Visit, Visitor,
+};
+use std::{collections::HashMap, ops::ControlFlow, process};
+
+lazy_static! {
+    static ref DOLLAR_REGEX: Regex = Regex::new("\\$\\{([^}]*)\\}|\\$([A-Za-z_][^$\\s]*)\\$")
+        .unwrap_or_else(|e| {
+            warn!("Unable to parse the regex: {e}");
+            process::exit(-1);
+        });
+}
+
+/// 检查规则
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rule {
+    /// `SELECT *`
+    SelectStar,
+    /// `UPDATE`/`DELETE` 没有 `WHERE`
+    MissingWhere,
+    /// `UPDATE`/`DELETE` 在某些条件组合下没有限制条件
+    LostWhere,
+    /// `LIKE '%...'`
+    LeadingWildcard,
+    /// 条件中对字段使用函数
+    FunctionOnColumn,
+    /// `<foreach>` 生成的 `IN` 列表没有判断集合大小
+    UnguardedForeach,
+    /// `ORDER BY ${...}`
+    OrderByDollar,
+    /// 多表之间没有关联条件
+    CartesianJoin,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 8] = [
+        Rule::SelectStar,
+        Rule::MissingWhere,
+        Rule::LostWhere,
+        Rule::LeadingWildcard,
+        Rule::FunctionOnColumn,
+        Rule::UnguardedForeach,
+        Rule::OrderByDollar,
+        Rule::CartesianJoin,
+    ];
+
+    pub fn from(name: &str) -> Option<Self> {
+        Rule::ALL.into_iter().find(|r| r.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::SelectStar => "select-star",
+            Rule::MissingWhere => "missing-where",
+            Rule::LostWhere => "lost-where",
+            Rule::LeadingWildcard => "leading-wildcard",
+            Rule::FunctionOnColumn => "function-on-column",
+            Rule::UnguardedForeach => "unguarded-foreach",
+            Rule::OrderByDollar => "order-by-dollar",
+            Rule::CartesianJoin => "cartesian-join",
+        }
+    }
+
+    /// 依赖语法树，语句无法解析时无从检查
+    fn needs_syntax_tree(&self) -> bool {
+        matches!(self, Rule::FunctionOnColumn | Rule::CartesianJoin)
+    }
+
+    fn default_level(&self) -> Level {
+        match self {
+            Rule::MissingWhere | Rule::LostWhere | Rule::OrderByDollar => Level::Error,
+            _ => Level::Warn,
+        }
+    }
+}
+
+/// 规则级别，`error` 时以非零值退出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Off,
+    Warn,
+    Error,
+}
+
+impl Level {
+    pub fn from(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(Level::Off),
+            "warn" => Some(Level::Warn),
+            "error" => Some(Level::Error),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Level::Off => "off",
+            Level::Warn => "warn",
+            Level::Error => "error",
+        }
+    }
+}
+
+/// 单条问题，以文件与 `STAT-ID` 定位
+#[derive(Serialize)]
+pub struct LintFinding {
+    pub file: String,
+    pub line: u64,
+    pub stat_id: String,
+    pub key: String,
+    pub rule: &'static str,
+    pub level: Level,
+    pub message: String,
+}
+
+/// 检查报告
+#[derive(Serialize, Default)]
+pub struct LintReport {
+    pub total: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    /// 无法解析、未经语法树规则检查的语句数
+    pub not_analysed: usize,
+    pub findings: Vec<LintFinding>,
+}
+
+/// 逐条检查语句，`levels` 覆盖规则的默认级别
+pub fn lint(
+    sqls: &[ExtractedSql],
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+    levels: &[(Rule, Level)],
+) -> LintReport {
+    let mut report = LintReport {
+        total: sqls.len(),
+        ..Default::default()
+    };
+    for stat in sqls {
+        let tokens = lexer::tokenize(&stat.sql, dialect_type, syntax);
+        let statements = match sql_validator::parse(&stat.template, dialect_type) {
+            Ok(statements) => statements,
+            Err(e) => {
+                if Rule::ALL
+                    .iter()
+                    .any(|r| r.needs_syntax_tree() && level_of(*r, levels) != Level::Off)
+                {
+                    warn!("{} not analysed by syntax rules: {e}", stat.key());
+                    report.not_analysed += 1;
+                }
+                Vec::new()
+            }
+        };
+        for rule in Rule::ALL {
+            let level = level_of(rule, levels);
+            if level == Level::Off {
+                continue;
+            }
+            let message = match rule {
+                Rule::SelectStar => select_star(stat, dialect_type),
+                Rule::MissingWhere => missing_where(&tokens),
+                Rule::LostWhere => lost_where(stat, &tokens, dialect_type, syntax),
+                Rule::LeadingWildcard => leading_wildcard(&tokens),
+                Rule::FunctionOnColumn => function_on_column(&statements),
+                Rule::UnguardedForeach => unguarded_foreach(&stat.dynamic),
+                Rule::OrderByDollar => order_by_dollar(stat, dialect_type, syntax),
+                Rule::CartesianJoin => cartesian_join(&statements),
+            };
+            if let Some(message) = message {
+                match level {
+                    Level::Error => report.errors += 1,
+                    _ => report.warnings += 1,
+                }
+                report.findings.push(LintFinding {
+                    file: stat.file.clone(),
+                    line: stat.line,
+                    stat_id: stat.id.clone(),
+                    key: stat.key(),
+                    rule: rule.name(),
+                    level,
+                    message,
+                });
+            }
+        }
+    }
+    report
+}
+
+/// 规则的级别，后出现的覆盖先出现的
+fn level_of(rule: Rule, levels: &[(Rule, Level)]) -> Level {
+    levels
+        .iter()
+        .rev()
+        .find(|(r, _)| *r == rule)
+        .map(|(_, l)| *l)
+        .unwrap_or(rule.default_level())
+}
+
+fn select_star(stat: &ExtractedSql, dialect_type: &DialectType) -> Option<String> {
+    impact_analyzer::has_wildcard(&stat.sql, dialect_type)
+        .then(|| String::from("SELECT * reads every column, list the columns instead"))
+}
+
+fn missing_where(tokens: &[Token]) -> Option<String> {
+    let kind = statement_kind(tokens)?;
+    (!has_top_where(tokens)).then(|| format!("{kind} without WHERE affects every row"))
+}
+
+/// 括号外是否有 `WHERE`
+fn has_top_where(tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    for token in tokens {
+        if token.is_symbol("(") {
+            depth += 1;
+        } else if token.is_symbol(")") {
+            depth -= 1;
+        } else if depth == 0 && token.is_word("WHERE") {
+            return true;
+        }
+    }
+    false
+}
+
+fn lost_where(
+    stat: &ExtractedSql,
+    tokens: &[Token],
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+) -> Option<String> {
+    let kind = statement_kind(tokens)?;
+    // 完全没有 `WHERE` 的语句由 `missing-where` 报告
+    if !has_top_where(tokens) {
+        return None;
+    }
+    let lost = dynamic_where::lost_where(stat, dialect_type, syntax)?;
+    if lost.conditions.is_empty() {
+        Some(format!(
+            "WHERE of {kind} does not restrict any row: {}",
+            lost.sql
+        ))
+    } else {
+        Some(format!(
+            "{kind} affects every row when {}: {}",
+            lost.conditions.join(", "),
+            lost.sql
+        ))
+    }
+}
+
+/// `UPDATE`/`DELETE`，其余语句为 `None`
+fn statement_kind(tokens: &[Token]) -> Option<String> {
+    let kind = tokens
+        .iter()
+        .find(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Hint))?;
+    (kind.is_word("UPDATE") || kind.is_word("DELETE")).then(|| kind.text.to_uppercase())
+}
+
+fn leading_wildcard(tokens: &[Token]) -> Option<String> {
+    let code: Vec<&Token> = tokens
+        .iter()
+        .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Hint))
+        .collect();
+    for (i, token) in code.iter().enumerate() {
+        if !token.is_word("LIKE") {
+            continue;
+        }
+        let pattern = match code.get(i + 1) {
+            Some(t) if t.is_word("CONCAT") && code.get(i + 2).is_some_and(|t| t.is_symbol("(")) => {
+                code.get(i + 3)
+            }
+            t => t,
+        };
+        if pattern.is_some_and(|t| t.kind == TokenKind::StringLit && t.text.starts_with("'%")) {
+            return Some(String::from(
+                "LIKE pattern starts with %, the index on the column can not be used",
+            ));
+        }
+    }
+    None
+}
+
+fn function_on_column(statements: &[Statement]) -> Option<String> {
+    let mut finder = FunctionFinder { names: Vec::new() };
+    for statement in statements {
+        let _ = statement.visit(&mut finder);
+    }
+    (!finder.names.is_empty()).then(|| {
+        format!(
+            "{} on columns in conditions, the index on the column can not be used",
+            finder.names.join(", ")
+        )
+    })
+}
+
+/// 条件中与参数、常量比较的一侧为包住字段的函数，如 `UPPER(name) = ?`
+struct FunctionFinder {
+    names: Vec<String>,
+}
+
+impl FunctionFinder {
+    fn check(&mut self, predicate: &Expr) {
+        let _ = sqlparser::ast::visit_expressions(predicate, |expr| {
+            let operands: Vec<(&Expr, Option<&Expr>)> = match expr {
+                Expr::BinaryOp { left, op, right } if is_comparison(op) => {
+                    vec![(left, Some(right)), (right, Some(left))]
+                }
+                Expr::Like { expr, .. }
+                | Expr::ILike { expr, .. }
+                | Expr::InList { expr, .. }
+                | Expr::Between { expr, .. } => vec![(expr, None)],
+                _ => Vec::new(),
+            };
+            for (side, other) in operands {
+                if let Some(name) = function_name(side) {
+                    if has_column(side)
+                        && !other.is_some_and(has_column)
+                        && !self.names.contains(&name)
+                    {
+                        self.names.push(name);
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+}
+
+impl Visitor for FunctionFinder {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        for select in selects(&query.body) {
+            for predicate in select_predicates(select) {
+                self.check(predicate);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<Self::Break> {
+        match statement {
+            Statement::Update {
+                selection: Some(selection),
+                ..
+            } => self.check(selection),
+            Statement::Delete(delete) => {
+                if let Some(selection) = &delete.selection {
+                    self.check(selection);
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+fn is_comparison(op: &BinaryOperator) -> bool {
+    matches!(
+        op,
+        BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq
+    )
+}
+
+fn function_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Function(f) => Some(f.name.to_string().to_uppercase()),
+        Expr::Cast { .. } => Some(String::from("CAST")),
+        Expr::Substring { .. } => Some(String::from("SUBSTRING")),
+        Expr::Trim { .. } => Some(String::from("TRIM")),
+        Expr::Extract { .. } => Some(String::from("EXTRACT")),
+        _ => None,
+    }
+}
+
+fn has_column(expr: &Expr) -> bool {
+    sqlparser::ast::visit_expressions(expr, |e| match e {
+        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    })
+    .is_break()
+}
+
+/// 查询体中的 `SELECT`，集合运算两侧均计入，子查询由遍历单独访问
+fn selects(body: &SetExpr) -> Vec<&Select> {
+    match body {
+        SetExpr::Select(select) => vec![select],
+        SetExpr::SetOperation { left, right, .. } => {
+            let mut found = selects(left);
+            found.extend(selects(right));
+            found
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// `WHERE` 与 `JOIN ... ON` 条件
+fn select_predicates(select: &Select) -> Vec<&Expr> {
+    let mut predicates: Vec<&Expr> = select.selection.iter().collect();
+    for table in select.from.iter() {
+        for join in table.joins.iter() {
+            if let Some(JoinConstraint::On(on)) = join_constraint(&join.join_operator) {
+                predicates.push(on);
+            }
+        }
+    }
+    predicates
+}
+
+fn join_constraint(op: &JoinOperator) -> Option<&JoinConstraint> {
+    match op {
+        JoinOperator::Join(c)
+        | JoinOperator::Inner(c)
+        | JoinOperator::Left(c)
+        | JoinOperator::LeftOuter(c)
+        | JoinOperator::Right(c)
+        | JoinOperator::RightOuter(c)
+        | JoinOperator::FullOuter(c)
+        | JoinOperator::CrossJoin(c)
+        | JoinOperator::Semi(c)
+        | JoinOperator::LeftSemi(c)
+        | JoinOperator::RightSemi(c)
+        | JoinOperator::Anti(c)
+        | JoinOperator::LeftAnti(c)
+        | JoinOperator::RightAnti(c)
+        | JoinOperator::StraightJoin(c)
+        | JoinOperator::AsOf { constraint: c, .. } => Some(c),
+        JoinOperator::CrossApply | JoinOperator::OuterApply => None,
+    }
+}
+
+/// `IN` 列表由循环生成，且外层条件没有判断集合是否为空
+fn unguarded_foreach(node: &DynamicNode) -> Option<String> {
+    let mut found = Vec::new();
+    find_unguarded(node, &mut Vec::new(), &mut found);
+    (!found.is_empty()).then(|| {
+        format!(
+            "IN list from {} has no size guard, an empty collection yields IN ()",
+            found.join(", ")
+        )
+    })
+}
+
+fn find_unguarded<'a>(
+    node: &'a DynamicNode,
+    ancestors: &mut Vec<&'a DynamicNode>,
+    found: &mut Vec<String>,
+) {
+    let mut previous = "";
+    for part in node.children.iter() {
+        match part {
+            DynamicPart::Text(text) => {
+                if !text.trim().is_empty() {
+                    previous = text;
+                }
+            }
+            DynamicPart::Element(e) => {
+                if matches!(e.tag.as_str(), "foreach" | "iterate")
+                    && follows_in(previous)
+                    && !ancestors.iter().any(|a| guards(a, e))
+                {
+                    let collection = e.attr("collection").or(e.attr("property"));
+                    found.push(format!("<{} {}>", e.tag, collection.unwrap_or("parameter")));
+                }
+                ancestors.push(e);
+                find_unguarded(e, ancestors, found);
+                ancestors.pop();
+            }
+        }
+    }
+}
+
+/// 前文以 `IN` 或 `IN (` 结尾
+fn follows_in(text: &str) -> bool {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .flat_map(|w| {
+            w.split_inclusive('(')
+                .map(|w| w.trim().to_ascii_uppercase())
+        })
+        .collect();
+    match words.last().map(|w| w.as_str()) {
+        Some("IN") | Some("IN(") => true,
+        Some("(") => words.len() >= 2 && words[words.len() - 2] == "IN",
+        _ => false,
+    }
+}
+
+/// 外层条件是否判断了集合大小，`iBATIS` 为 `<isNotEmpty>`
+fn guards(ancestor: &DynamicNode, each: &DynamicNode) -> bool {
+    let collection = each.attr("collection").or(each.attr("property"));
+    if ancestor.tag == "isnotempty" {
+        return collection.is_none_or(|c| ancestor.attr("property") == Some(c));
+    }
+    let Some(test) = ancestor.attr("test") else {
+        return false;
+    };
+    let name = collection
+        .map(|c| c.rsplit('.').next().unwrap_or(c))
+        .unwrap_or("list");
+    let test = test.to_ascii_lowercase();
+    test.contains(&name.to_ascii_lowercase())
+        && ["size", "length", "empty"].iter().any(|w| test.contains(w))
+}
+
+fn order_by_dollar(
+    stat: &ExtractedSql,
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+) -> Option<String> {
+    let texts =
+        std::iter::once(stat.own_sql.as_str()).chain(stat.fragments.iter().map(|f| f.sql.as_str()));
+    for text in texts {
+        let substituted: Vec<&str> = DOLLAR_REGEX
+            .captures_iter(text)
+            .filter_map(|c| c.get(1).or(c.get(2)))
+            .map(|m| m.as_str().trim())
+            .collect();
+        if substituted.is_empty() {
+            continue;
+        }
+        let tokens = lexer::tokenize(text, dialect_type, syntax);
+        if let Some(expr) = order_by_placeholder(&tokens, &substituted) {
+            let expr = match syntax {
+                TemplateSyntax::IBatis => format!("${expr}$"),
+                TemplateSyntax::MyBatis => format!("${{{expr}}}"),
+            };
+            return Some(format!(
+                "ORDER BY uses text substitution {expr}, validate it against a whitelist"
+            ));
+        }
+    }
+    None
+}
+
+/// `ORDER BY` 之后、子句结束之前的文本替换
+fn order_by_placeholder<'a>(tokens: &'a [Token], substituted: &[&str]) -> Option<&'a str> {
+    let mut in_order_by = false;
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        if token.is_word("BY") && i > 0 && tokens[i - 1].is_word("ORDER") {
+            in_order_by = true;
+            depth = 0;
+        } else if !in_order_by {
+            continue;
+        } else if token.is_symbol("(") {
+            depth += 1;
+        } else if token.is_symbol(")") {
+            depth -= 1;
+            in_order_by = depth >= 0;
+        } else if [
+            "LIMIT",
+            "OFFSET",
+            "FETCH",
+            "FOR",
+            "UNION",
+            "MINUS",
+            "INTERSECT",
+            "EXCEPT",
+        ]
+        .iter()
+        .any(|w| token.is_word(w))
+        {
+            in_order_by = false;
+        } else if token.kind == TokenKind::Placeholder && substituted.contains(&token.text.as_str())
+        {
+            return Some(&token.text);
+        }
+    }
+    None
+}
+
+fn cartesian_join(statements: &[Statement]) -> Option<String> {
+    let mut finder = JoinFinder { groups: Vec::new() };
+    for statement in statements {
+        let _ = statement.visit(&mut finder);
+    }
+    finder
+        .groups
+        .first()
+        .map(|groups| format!("no join condition between {groups}, rows are multiplied"))
+}
+
+/// 同一 `SELECT` 的多个表之间，`ON` 与 `WHERE` 中的等值条件未能把它们连通
+struct JoinFinder {
+    groups: Vec<String>,
+}
+
+impl Visitor for JoinFinder {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        for select in selects(&query.body) {
+            if let Some(groups) = disconnected(select) {
+                self.groups.push(groups);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// 以并查集合并有关联的表，不止一组时返回各组
+fn disconnected(select: &Select) -> Option<String> {
+    let mut names: Vec<String> = Vec::new();
+    let mut parent: Vec<usize> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut add = |factor: &TableFactor, names: &mut Vec<String>, parent: &mut Vec<usize>| {
+        let name = factor_name(factor).unwrap_or(format!("#{}", names.len()));
+        index.insert(name.clone(), names.len());
+        names.push(name);
+        parent.push(parent.len());
+        parent.len() - 1
+    };
+    let mut links: Vec<(usize, usize)> = Vec::new();
+    for table in select.from.iter() {
+        let root = add(&table.relation, &mut names, &mut parent);
+        for join in table.joins.iter() {
+            let joined = add(&join.relation, &mut names, &mut parent);
+            let linked = match &join.join_operator {
+                JoinOperator::CrossJoin(JoinConstraint::None) => false,
+                op => join_constraint(op).is_some_and(|c| !matches!(c, JoinConstraint::None)),
+            };
+            if linked {
+                links.push((root, joined));
+            }
+        }
+    }
+    if names.len() < 2 {
+        return None;
+    }
+    if let Some(selection) = &select.selection {
+        let mut unqualified = false;
+        let _ = sqlparser::ast::visit_expressions(selection, |expr| {
+            if let Expr::BinaryOp {
+                left,
+                op: BinaryOperator::Eq,
+                right,
+            } = expr
+            {
+                match (qualifier(left), qualifier(right)) {
+                    (Some(Some(l)), Some(Some(r))) => {
+                        if let (Some(l), Some(r)) = (index.get(&l), index.get(&r)) {
+                            links.push((*l, *r));
+                        }
+                    }
+                    (Some(_), Some(_)) => unqualified = true,
+                    _ => {}
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        // 两侧均为未限定的字段时无法判断归属，视为已关联
+        if unqualified {
+            return None;
+        }
+    }
+    for (a, b) in links {
+        let (a, b) = (find(&mut parent, a), find(&mut parent, b));
+        parent[a] = b;
+    }
+    let mut groups: Vec<(usize, Vec<&str>)> = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let root = find(&mut parent, i);
+        match groups.iter_mut().find(|(r, _)| *r == root) {
+            Some((_, members)) => members.push(name),
+            None => groups.push((root, vec![name])),
+        }
+    }
+    (groups.len() > 1).then(|| {
+        groups
+            .iter()
+            .map(|(_, members)| members.join("+"))
+            .collect::<Vec<String>>()
+            .join(" and ")
+    })
+}
+
+fn find(parent: &mut [usize], i: usize) -> usize {
+    let mut root = i;
+    while parent[root] != root {
+        root = parent[root];
+    }
+    parent[i] = root;
+    root
+}
+
+/// 表的别名或表名，小写
+fn factor_name(factor: &TableFactor) -> Option<String> {
+    match factor {
+        TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } => Some(alias.name.value.to_lowercase()),
+        TableFactor::Table { name, .. } => name
+            .0
+            .last()
+            .and_then(|part| part.as_ident())
+            .map(|ident| ident.value.to_lowercase()),
+        _ => None,
+    }
+}
+
+/// 字段的限定名：`Some(Some(q))` 为 `q.col`，`Some(None)` 为未限定的字段，非字段为 `None`
+fn qualifier(expr: &Expr) -> Option<Option<String>> {
+    match expr {
+        Expr::Identifier(_) => Some(None),
+        Expr::CompoundIdentifier(idents) if idents.len() >= 2 => {
+            Some(Some(idents[idents.len() - 2].value.to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+/// 文本格式输出
+pub fn print_text(report: &LintReport) {
+    for finding in report.findings.iter() {
+        println!(
+            "{}:{}: STAT-ID: {} [{}] {}: {}",
+            finding.file,
+            finding.line,
+            finding.stat_id,
+            finding.level.name(),
+            finding.rule,
+            finding.message
+        );
+    }
+    println!(
+        "linted: {}, errors: {}, warnings: {}, not analysed: {}",
+        report.total, report.errors, report.warnings, report.not_analysed
+    );
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &LintReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize lint report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extracted(id: &str, template: &str) -> ExtractedSql {
+        ExtractedSql {
+            file: String::from("test.xml"),
+            namespace: String::from("m"),
+            id: String::from(id),
+            kind: String::from("select"),
+            sql: template.replace("#{id}", "?"),
+            template: String::from(template),
+            line: 1,
+            own_sql: String::from(template),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unparsed_statements_are_counted_as_not_analysed() {
+        let sqls = [
+            extracted("a", "SELECT a FROM t, u WHERE t.id = #{id}"),
+            extracted("b", "SELECT FROM t WHERE id = #{id} ((("),
+        ];
+        let report = lint(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, &[]);
+        assert_eq!(report.not_analysed, 1);
+        assert!(report
+            .findings
+            .iter()
+            .any(|f| f.key == "m.a" && f.rule == "cartesian-join"));
+        let levels = [
+            (Rule::FunctionOnColumn, Level::Off),
+            (Rule::CartesianJoin, Level::Off),
+        ];
+        let report = lint(&sqls, &DialectType::MySQL, TemplateSyntax::MyBatis, &levels);
+        assert_eq!(report.not_analysed, 0);
+    }
+}
@@ -1,6 +1,6 @@
 use log::LevelFilter;
 use log4rs::{
-    append::console::ConsoleAppender,
+    append::console::{ConsoleAppender, Target},
     config::{Appender, Root},
     encode::pattern::PatternEncoder,
     Config,
@@ -9,14 +9,21 @@ use std::{process, sync::Once};
 
 const LOG_FORMAT: &str = "[{d(%H:%M:%S.%9f)}][{h({l})}][{h({T})}] {m}{n}";
 
+static INIT: Once = Once::new();
+
 /// 日志初始化，写入 `stdout`，并写入临时文件夹下 `xbatis2sql.log`
 pub(crate) fn init_logger() {
-    static INIT: Once = Once::new();
-    INIT.call_once(init_log4rs);
+    INIT.call_once(|| init_log4rs(Target::Stdout));
+}
+
+/// 日志初始化，写入 `stderr`，供结果输出到 `stdout` 时使用
+pub(crate) fn init_stderr_logger() {
+    INIT.call_once(|| init_log4rs(Target::Stderr));
 }
 
-fn init_log4rs() {
+fn init_log4rs(target: Target) {
     let stdout_appender = ConsoleAppender::builder()
+        .target(target)
         .encoder(Box::new(PatternEncoder::new(LOG_FORMAT)))
         .build();
     let config = Config::builder()
@@ -2,26 +2,75 @@
 
 /// 解析参数
 mod args;
+/// 增量缓存
+mod cache;
+/// 方言兼容性
+mod compat;
+/// 比对
+mod diff;
+/// 重复语句
+mod duplicate;
+/// 执行计划
+mod explain;
+/// 结构化抽取
+mod extract;
+/// 映射文件整理
+mod hygiene;
+/// 影响分析
+mod impact;
+/// 表与字段清单
+mod inventory;
+/// 静态检查
+mod lint;
 /// 日志处置
 mod logit;
+/// 复杂度指标
+mod metrics;
 /// 保存
 mod save;
 /// 扫描器
 mod scan;
+/// `sql` 词法分析与规整
+mod sql;
+/// 语法校验
+mod validate;
+/// 监听
+mod watch;
 /// 解析器
 mod xbatis;
 
 use crate::{
-    args::args_parser::{self, Args, DbType, XBatisMode},
+    args::args_parser::{self, Args, Command, DbType, ReportFormat, XBatisMode},
+    cache::parsed_cache::ParsedCache,
+    compat::compat_checker,
+    diff::{git_tree, sql_diff},
+    duplicate::duplicate_finder,
+    explain::{database, plan_runner},
+    extract::sql_extractor::{self, ExtractedSql},
+    hygiene::mapper_hygiene,
+    impact::impact_analyzer,
+    inventory::table_inventory,
+    lint::sql_linter,
     logit::log_initializer,
+    metrics::complexity_metrics,
     save::sql_saver,
     scan::xml_scanner,
-    xbatis::{def::DialectType, ibatis_parser, mybatis_parser, xbatis_parser::Parser},
+    sql::{normalizer::NormalizeOptions, sample::SampleValues},
+    validate::sql_validator,
+    watch::xml_watcher,
+    xbatis::{
+        def::{DialectType, ParsedXml},
+        ibatis_parser, mybatis_parser,
+        xbatis_parser::Parser,
+    },
 };
 use concurrent_queue::ConcurrentQueue;
 use log::{info, warn};
 use std::{
     collections::HashMap,
+    io::{self, Write},
+    path::Path,
+    process,
     sync::{
         atomic::{AtomicBool, AtomicI16, Ordering},
         Arc, Mutex,
@@ -37,24 +86,280 @@ fn main() {
         args_parser::print_usage(&options);
     } else if args.show_version {
         args_parser::print_version();
+    } else if let Command::Diff { base, head } = &args.command {
+        diff_xbatis_xml(&args, base, head);
+    } else if let Command::ExplainRun { url } = &args.command {
+        explain_run_xbatis_xml(&args, url);
+    } else if let Command::Compat { target } = &args.command {
+        compat_xbatis_xml(&args, *target);
+    } else if args.command == Command::Inventory {
+        inventory_xbatis_xml(&args);
+    } else if let Command::Impact { table, column } = &args.command {
+        impact_xbatis_xml(&args, table, column.as_deref());
+    } else if args.command == Command::Lint {
+        lint_xbatis_xml(&args);
+    } else if args.command == Command::Hygiene {
+        hygiene_xbatis_xml(&args);
+    } else if let Command::Duplicates { threshold } = &args.command {
+        duplicates_xbatis_xml(&args, *threshold);
+    } else if args.command == Command::Metrics {
+        metrics_xbatis_xml(&args);
+    } else if args.sources.stdin {
+        parse_stdin_xml(&args);
+    } else if args.watch {
+        watch_xbatis_xml(&args);
     } else {
         parse_xbatis_xml(&args);
     }
 }
 
+/// 监听源码目录，变化时增量更新 `result.sql`
+fn watch_xbatis_xml(args: &Args) {
+    log_initializer::init_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    xml_watcher::watch(args, parser.as_ref(), &files);
+}
+
+/// 比对两侧抽取出的 `sql`，报告输出到 `stdout`
+fn diff_xbatis_xml(args: &Args, base: &str, head: &str) {
+    log_initializer::init_stderr_logger();
+    let parser = create_parser(args);
+    let base_tree = git_tree::prepare(base, "base");
+    let head_tree = git_tree::prepare(head, "head");
+    let base_sqls = extract_tree(parser.as_ref(), &base_tree.dir);
+    let head_sqls = extract_tree(parser.as_ref(), &head_tree.dir);
+    let report = sql_diff::diff(&base_sqls, &head_sqls);
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => sql_diff::print_text(&report),
+        ReportFormat::Json => sql_diff::print_json(&report),
+    }
+}
+
+/// 连接数据库逐条执行 `EXPLAIN`，报告输出到 `stdout`，指定了输出目录时另存为 `plans.json`
+fn explain_run_xbatis_xml(args: &Args, url: &str) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let mut db = database::connect(url).unwrap_or_else(|e| {
+        warn!("connect to database failed: {e}");
+        process::exit(-1);
+    });
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = plan_runner::run(db.as_mut(), &sqls);
+    if !args.output_dir.is_empty() {
+        plan_runner::save(&report, &args.output_dir);
+    }
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => plan_runner::print_text(&report),
+        ReportFormat::Json => plan_runner::print_json(&report),
+    }
+}
+
+/// 检查语句中目标方言不支持的写法，报告输出到 `stdout`
+fn compat_xbatis_xml(args: &Args, target: DbType) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = compat_checker::check(&sqls, parser.dialect_type(), &convert(target));
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => compat_checker::print_text(&report),
+        ReportFormat::Json => compat_checker::print_json(&report),
+    }
+}
+
+/// 汇总各语句读写的表、字段与关联条件，报告输出到 `stdout`
+fn inventory_xbatis_xml(args: &Args) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = table_inventory::inventory(&sqls, parser.dialect_type());
+    match args.format {
+        ReportFormat::Text => table_inventory::print_text(&report),
+        ReportFormat::Json => table_inventory::print_json(&report),
+        ReportFormat::Csv => table_inventory::print_csv(&report),
+    }
+}
+
+/// 列出读写指定表（及字段）的语句，报告输出到 `stdout`
+fn impact_xbatis_xml(args: &Args, table: &str, column: Option<&str>) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = impact_analyzer::analyze(
+        &sqls,
+        parser.dialect_type(),
+        parser.template_syntax(),
+        table,
+        column,
+    );
+    match args.format {
+        ReportFormat::Text => impact_analyzer::print_text(&report),
+        ReportFormat::Json => impact_analyzer::print_json(&report),
+        ReportFormat::Csv => impact_analyzer::print_csv(&report),
+    }
+}
+
+/// 按规则检查每条语句，存在 `error` 级别的问题时以非零值退出
+fn lint_xbatis_xml(args: &Args) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = sql_linter::lint(
+        &sqls,
+        parser.dialect_type(),
+        parser.template_syntax(),
+        &args.rules,
+    );
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => sql_linter::print_text(&report),
+        ReportFormat::Json => sql_linter::print_json(&report),
+    }
+    if report.errors > 0 {
+        process::exit(1);
+    }
+}
+
+/// 报告未引用的片段、找不到的引用与重复的语句或片段 `id`，报告输出到 `stdout`
+fn hygiene_xbatis_xml(args: &Args) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let parsed_files: Vec<(String, ParsedXml)> = files
+        .iter()
+        .filter_map(|file| {
+            sql_extractor::parse_file(parser.as_ref(), file).map(|p| (file.clone(), p))
+        })
+        .collect();
+    let report = mapper_hygiene::check(&parsed_files);
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => mapper_hygiene::print_text(&report),
+        ReportFormat::Json => mapper_hygiene::print_json(&report),
+    }
+}
+
+/// 按规范形式的相似度将语句分组，报告输出到 `stdout`
+fn duplicates_xbatis_xml(args: &Args, threshold: f64) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = duplicate_finder::find(
+        &sqls,
+        parser.dialect_type(),
+        parser.template_syntax(),
+        threshold,
+    );
+    match args.format {
+        ReportFormat::Text => duplicate_finder::print_text(&report),
+        ReportFormat::Json => duplicate_finder::print_json(&report),
+        ReportFormat::Csv => duplicate_finder::print_csv(&report),
+    }
+}
+
+/// 计算每条语句的复杂度指标，按参数排序、过滤，报告输出到 `stdout`
+fn metrics_xbatis_xml(args: &Args) {
+    log_initializer::init_stderr_logger();
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::collect(&mut files, &args.sources.dirs, &args.sources.files_from);
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), &files);
+    let report = complexity_metrics::measure(
+        &sqls,
+        parser.dialect_type(),
+        parser.template_syntax(),
+        args.sort,
+        &args.minimums,
+    );
+    match args.format {
+        ReportFormat::Text => complexity_metrics::print_text(&report),
+        ReportFormat::Json => complexity_metrics::print_json(&report),
+        ReportFormat::Csv => complexity_metrics::print_csv(&report),
+    }
+}
+
+/// 抽取目录下全部语句，文件名取相对目录的路径，便于两侧对照
+fn extract_tree(parser: &dyn Parser, dir: &String) -> Vec<ExtractedSql> {
+    let mut files: Vec<String> = Vec::new();
+    xml_scanner::scan(&mut files, dir);
+    let mut sqls = sql_extractor::extract(parser, &files);
+    for sql in sqls.iter_mut() {
+        if let Ok(relative) = Path::new(&sql.file).strip_prefix(dir) {
+            sql.file = relative.to_string_lossy().to_string();
+        }
+    }
+    sqls
+}
+
+/// 从 `stdin` 读取单个 `xml` 文档，结果输出到 `stdout`
+fn parse_stdin_xml(args: &Args) {
+    log_initializer::init_stderr_logger();
+    let parser = create_parser(args);
+    let arc_global_inc_map: Arc<Mutex<HashMap<String, String>>> =
+        Arc::new(Mutex::new(HashMap::new()));
+    let mut sql_store: Vec<String> = Vec::new();
+    if let Ok(mut global_inc_map) = arc_global_inc_map.lock() {
+        parser.read_xml(
+            &String::from("<stdin>"),
+            &mut io::stdin().lock(),
+            &mut sql_store,
+            &mut global_inc_map,
+        );
+    }
+    sql_store.extend(parser.explain_epilogue());
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    let mut query_no = 0;
+    for sql in sql_store {
+        let line = parser.replace_final_sql(arc_global_inc_map.clone(), &sql, &mut query_no);
+        if writeln!(out, "{line}").is_err() {
+            warn!("write to stdout failed");
+            process::exit(-1);
+        }
+    }
+}
+
 /// 选择并执行对应的解析器
 fn parse_xbatis_xml(args: &Args) {
-    let src_dir = &args.src_dir;
+    let sources = &args.sources;
     let output_dir = &args.output_dir;
-    log_initializer::init_logger();
-    info!("try to parse files in {src_dir:?}, fetch sql to {output_dir:?}");
+    // 校验报告输出到 `stdout`，日志改写到 `stderr`
+    if args.validate {
+        log_initializer::init_stderr_logger();
+    } else {
+        log_initializer::init_logger();
+    }
+    info!(
+        "try to parse files in {:?} (file list: {:?}), fetch sql to {output_dir:?}",
+        sources.dirs, sources.files_from
+    );
     let mut files: Vec<String> = Vec::new();
-    xml_scanner::scan(&mut files, src_dir);
+    xml_scanner::collect(&mut files, &sources.dirs, &sources.files_from);
     let arc_queue = Arc::new(ConcurrentQueue::<Vec<String>>::unbounded());
     let arc_limit = Arc::new(AtomicI16::new(0));
     let arc_active = Arc::new(AtomicBool::new(true));
     let arc_global_inc_map: Arc<Mutex<HashMap<String, String>>> =
         Arc::new(Mutex::new(HashMap::new()));
+    let arc_cache = if args.incremental {
+        Some(Arc::new(ParsedCache::load(
+            output_dir,
+            format!("{:?}/{:?}", args.mode, args.db_type),
+        )))
+    } else {
+        None
+    };
     let output_dir_clone = output_dir.clone();
     let arc_queue_writer_clone = arc_queue.clone();
     let arc_active_writer_clone = arc_active.clone();
@@ -68,19 +373,53 @@ fn parse_xbatis_xml(args: &Args) {
             )
         })
         .unwrap();
-    for file in files {
+    for file in files.iter().cloned() {
         let arc_limit_clone = arc_limit.clone();
         while arc_limit_clone.load(Ordering::SeqCst) >= 8 {
             thread::sleep(Duration::from_millis(100));
         }
-        loop_parse_handle(args, &arc_queue, &arc_limit, &arc_global_inc_map, file);
+        loop_parse_handle(
+            args,
+            &arc_queue,
+            &arc_limit,
+            &arc_global_inc_map,
+            &arc_cache,
+            file,
+        );
     }
-    while arc_limit.load(Ordering::SeqCst) > 0 && !arc_queue.is_empty() {
+    while arc_limit.load(Ordering::SeqCst) > 0 {
+        thread::sleep(Duration::from_millis(100));
+    }
+    let epilogue = create_parser(args).explain_epilogue();
+    if !epilogue.is_empty() && arc_queue.push(epilogue).is_err() {
+        warn!("push to queue failed");
+    }
+    while !arc_queue.is_empty() {
         thread::sleep(Duration::from_millis(100));
     }
     arc_active.store(false, Ordering::SeqCst);
     handler.join().unwrap();
     sql_saver::rewrite(create_parser(args), arc_global_inc_map);
+    if let Some(cache) = arc_cache {
+        cache.save();
+    }
+    if args.validate {
+        validate_xbatis_xml(args, &files);
+    }
+}
+
+/// 以方言语法校验每条语句，存在语法错误时以非零值退出
+fn validate_xbatis_xml(args: &Args, files: &[String]) {
+    let parser = create_parser(args);
+    let sqls = sql_extractor::extract(parser.as_ref(), files);
+    let report = sql_validator::validate(&sqls, parser.dialect_type());
+    match args.format {
+        ReportFormat::Text | ReportFormat::Csv => sql_validator::print_text(&report),
+        ReportFormat::Json => sql_validator::print_json(&report),
+    }
+    if !report.failures.is_empty() {
+        process::exit(1);
+    }
 }
 
 fn write_handle(
@@ -110,9 +449,11 @@ fn loop_parse_handle(
     arc_queue: &Arc<ConcurrentQueue<Vec<String>>>,
     arc_limit: &Arc<AtomicI16>,
     arc_global_inc_map: &Arc<Mutex<HashMap<String, String>>>,
+    arc_cache: &Option<Arc<ParsedCache>>,
     file: String,
 ) {
     let args_clone = args.clone();
+    let arc_cache_clone = arc_cache.clone();
     let arc_limit_clone = arc_limit.clone();
     let arc_queue_clone = arc_queue.clone();
     let arc_global_inc_map_clone = arc_global_inc_map.clone();
@@ -125,6 +466,7 @@ fn loop_parse_handle(
             arc_limit_clone,
             arc_queue_clone,
             arc_global_inc_map_clone,
+            arc_cache_clone,
         )
     });
 }
@@ -135,9 +477,14 @@ fn parse_handle(
     arc_limit: Arc<AtomicI16>,
     arc_queue: Arc<ConcurrentQueue<Vec<String>>>,
     arc_global_inc_map_clone: Arc<Mutex<HashMap<String, String>>>,
+    arc_cache: Option<Arc<ParsedCache>>,
 ) {
     let parser = create_parser(&args);
-    if let Some(sql_store) = parser.parse(&file.clone(), arc_global_inc_map_clone) {
+    let parsed = match arc_cache {
+        Some(cache) => cache.parse(parser.as_ref(), &file, &arc_global_inc_map_clone),
+        None => parser.parse(&file.clone(), arc_global_inc_map_clone),
+    };
+    if let Some(sql_store) = parsed {
         while arc_queue.len() >= 100 {
             thread::sleep(Duration::from_millis(100));
         }
@@ -158,8 +505,21 @@ fn create_parser(args: &Args) -> Box<dyn Parser> {
     let sql_limit = args.sql_limit;
     let mut parser = choose_parser(mode, convert(db_type));
     parser.setup_gen_explain(gen_explain);
+    parser.setup_explain_format(args.explain_format);
     parser.setup_replace_num(replace_num);
     parser.setup_sql_limit(sql_limit);
+    parser.setup_normalize_options(NormalizeOptions {
+        keep_comments: args.keep_comments,
+        pretty: args.pretty,
+        case: args.case,
+        placeholder: args.placeholder,
+        sample_values: args
+            .sample_values
+            .then(|| SampleValues::new(&convert(db_type), &args.samples)),
+        prologue: args
+            .prologue
+            .then(|| SampleValues::new(&convert(db_type), &args.samples)),
+    });
     parser
 }
 
@@ -175,6 +535,14 @@ fn convert(db_type: DbType) -> DialectType {
     match db_type {
         DbType::Oracle => DialectType::Oracle,
         DbType::MySQL => DialectType::MySQL,
+        DbType::Sqlite => DialectType::Sqlite,
+        DbType::H2 => DialectType::H2,
+        DbType::Dm => DialectType::Dm,
+        DbType::Kingbase => DialectType::Kingbase,
+        DbType::OpenGauss => DialectType::OpenGauss,
+        DbType::OceanBaseOracle => DialectType::OceanBaseOracle,
+        DbType::OceanBaseMySql => DialectType::OceanBaseMySql,
+        DbType::Db2 => DialectType::Db2,
         _ => panic!("unknown dialect type"),
     }
 }
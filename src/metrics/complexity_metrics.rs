@@ -0,0 +1,508 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    save::csv_writer,
+    sql::lexer::{self, TemplateSyntax, TokenKind},
+    validate::sql_validator,
+    xbatis::def::{DialectType, DynamicNode, DynamicPart},
+};
+use log::warn;
+use serde::Serialize;
+use sqlparser::ast::{
+    FromTable, Query, SetExpr, Statement, TableWithJoins, UpdateTableFromKind, Visit, Visitor,
+};
+use std::{cmp::Reverse, ops::ControlFlow};
+
+/// 复杂度指标
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Metric {
+    /// 表连接数，逗号分隔的多表同样计入
+    Joins,
+    /// 子查询嵌套层数，`WITH` 定义的查询与语句本身同层
+    SubqueryDepth,
+    /// 条件元素数，`<if>`、`<when>`、`<otherwise>`、循环与 `<isXxx>`
+    Branches,
+    /// `UNION`/`INTERSECT`/`EXCEPT`/`MINUS` 数
+    Unions,
+    /// `include` 嵌套层数
+    IncludeDepth,
+    /// 规整后 `sql` 的长度，与 `-l` 一致
+    Length,
+    /// 替换 `include` 后的参数占位符数
+    Params,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 7] = [
+        Metric::Joins,
+        Metric::SubqueryDepth,
+        Metric::Branches,
+        Metric::Unions,
+        Metric::IncludeDepth,
+        Metric::Length,
+        Metric::Params,
+    ];
+
+    pub fn from(name: &str) -> Option<Self> {
+        Metric::ALL.into_iter().find(|m| m.name() == name)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Joins => "joins",
+            Metric::SubqueryDepth => "subquery-depth",
+            Metric::Branches => "branches",
+            Metric::Unions => "unions",
+            Metric::IncludeDepth => "include-depth",
+            Metric::Length => "length",
+            Metric::Params => "params",
+        }
+    }
+
+    fn value(&self, metrics: &StatementMetrics) -> usize {
+        match self {
+            Metric::Joins => metrics.joins,
+            Metric::SubqueryDepth => metrics.subquery_depth,
+            Metric::Branches => metrics.branches,
+            Metric::Unions => metrics.unions,
+            Metric::IncludeDepth => metrics.include_depth,
+            Metric::Length => metrics.length,
+            Metric::Params => metrics.params,
+        }
+    }
+}
+
+/// 单条语句的指标
+#[derive(Serialize)]
+pub struct StatementMetrics {
+    pub file: String,
+    pub line: u64,
+    pub key: String,
+    pub kind: String,
+    pub joins: usize,
+    pub subquery_depth: usize,
+    pub branches: usize,
+    pub unions: usize,
+    pub include_depth: usize,
+    pub length: usize,
+    pub params: usize,
+    /// 无法解析，连接、子查询与集合运算按词估算
+    pub estimated: bool,
+}
+
+/// 指标报告
+#[derive(Serialize, Default)]
+pub struct MetricsReport {
+    pub total: usize,
+    pub statements: Vec<StatementMetrics>,
+}
+
+/// 计算每条语句的指标；有阈值时只保留达到任一阈值的语句，有排序指标时按其从大到小排列
+pub fn measure(
+    sqls: &[ExtractedSql],
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+    sort: Option<Metric>,
+    minimums: &[(Metric, usize)],
+) -> MetricsReport {
+    let mut report = MetricsReport {
+        total: sqls.len(),
+        ..Default::default()
+    };
+    for stat in sqls {
+        let metrics = metrics_of(stat, dialect_type, syntax);
+        if minimums.is_empty() || minimums.iter().any(|(m, min)| m.value(&metrics) >= *min) {
+            report.statements.push(metrics);
+        }
+    }
+    if let Some(metric) = sort {
+        report
+            .statements
+            .sort_by_key(|metrics| Reverse(metric.value(metrics)));
+    }
+    report
+}
+
+fn metrics_of(
+    stat: &ExtractedSql,
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+) -> StatementMetrics {
+    let mut metrics = StatementMetrics {
+        file: stat.file.clone(),
+        line: stat.line,
+        key: stat.key(),
+        kind: stat.kind.clone(),
+        joins: 0,
+        subquery_depth: 0,
+        branches: branches(&stat.dynamic),
+        unions: 0,
+        include_depth: stat.fragments.iter().map(|f| f.depth).max().unwrap_or(0),
+        length: stat.sql.len(),
+        params: lexer::tokenize(&stat.template, dialect_type, syntax)
+            .iter()
+            .filter(|t| t.kind == TokenKind::Placeholder)
+            .count(),
+        estimated: false,
+    };
+    match sql_validator::parse(&stat.template, dialect_type) {
+        Ok(statements) => {
+            for statement in statements.iter() {
+                let mut counter = Counter::default();
+                let _ = statement.visit(&mut counter);
+                // 查询本身与 `INSERT ... SELECT` 的来源不算子查询
+                let top = match statement {
+                    Statement::Query(_) => 1,
+                    Statement::Insert(insert) if insert.source.is_some() => 1,
+                    _ => 0,
+                };
+                metrics.joins += counter.joins;
+                metrics.unions += counter.unions;
+                metrics.subquery_depth = metrics
+                    .subquery_depth
+                    .max(counter.max_depth.saturating_sub(top));
+            }
+        }
+        Err(_) => {
+            metrics.estimated = true;
+            estimate(&mut metrics, &stat.sql, dialect_type, syntax);
+        }
+    }
+    metrics
+}
+
+fn branches(node: &DynamicNode) -> usize {
+    node.children
+        .iter()
+        .map(|part| match part {
+            DynamicPart::Element(e) => usize::from(e.is_conditional()) + branches(e),
+            DynamicPart::Text(_) => 0,
+        })
+        .sum()
+}
+
+/// 结束 `FROM` 表列表的子句
+const FROM_ENDS: [&str; 12] = [
+    "WHERE",
+    "GROUP",
+    "HAVING",
+    "ORDER",
+    "LIMIT",
+    "UNION",
+    "INTERSECT",
+    "EXCEPT",
+    "MINUS",
+    "SET",
+    "CONNECT",
+    "START",
+];
+
+/// 按词估算：`JOIN` 与 `FROM` 表列表中的逗号计为连接，括号内的 `SELECT` 计为子查询，
+/// `AS (` 之后为 `WITH` 定义的查询，不计层数
+fn estimate(
+    metrics: &mut StatementMetrics,
+    sql: &str,
+    dialect_type: &DialectType,
+    syntax: TemplateSyntax,
+) {
+    // 未闭合的括号是否计入子查询层数
+    let mut parens: Vec<bool> = Vec::new();
+    // 正处于 `FROM` 表列表中的括号层
+    let mut from_lists: Vec<usize> = Vec::new();
+    let mut after_as = false;
+    for token in lexer::tokenize(sql, dialect_type, syntax) {
+        if token.kind == TokenKind::Comment || token.kind == TokenKind::Hint {
+            continue;
+        }
+        if token.is_symbol("(") {
+            parens.push(!after_as);
+        } else if token.is_symbol(")") {
+            parens.pop();
+            from_lists.retain(|level| *level <= parens.len());
+        } else if token.is_symbol(",") {
+            if from_lists.last() == Some(&parens.len()) {
+                metrics.joins += 1;
+            }
+        } else if token.is_word("FROM") {
+            from_lists.push(parens.len());
+        } else if token.is_word("JOIN") {
+            metrics.joins += 1;
+        } else if token.is_word("SELECT") {
+            let depth = parens.iter().filter(|counted| **counted).count();
+            metrics.subquery_depth = metrics.subquery_depth.max(depth);
+        }
+        if FROM_ENDS.iter().any(|w| token.is_word(w)) && from_lists.last() == Some(&parens.len()) {
+            from_lists.pop();
+        }
+        if ["UNION", "INTERSECT", "EXCEPT", "MINUS"]
+            .iter()
+            .any(|w| token.is_word(w))
+        {
+            metrics.unions += 1;
+        }
+        after_as = token.is_word("AS");
+    }
+}
+
+/// 遍历语句，累计连接与集合运算，记录查询的最大嵌套层数
+#[derive(Default)]
+struct Counter {
+    depth: usize,
+    max_depth: usize,
+    /// 尚未遍历的 `WITH` 定义的查询，只比较地址
+    ctes: Vec<*const Query>,
+    /// 各层未结束的查询是否计入了层数
+    counted: Vec<bool>,
+    joins: usize,
+    unions: usize,
+}
+
+impl Counter {
+    fn count_body(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.count_from(&select.from),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.unions += 1;
+                self.count_body(left);
+                self.count_body(right);
+            }
+            _ => {}
+        }
+    }
+
+    fn count_from(&mut self, from: &[TableWithJoins]) {
+        self.joins += from.iter().map(|t| t.joins.len()).sum::<usize>();
+        self.joins += from.len().saturating_sub(1);
+    }
+}
+
+impl Visitor for Counter {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        let address: *const Query = query;
+        let is_cte = self.ctes.contains(&address);
+        self.ctes.retain(|cte| *cte != address);
+        if let Some(with) = &query.with {
+            self.ctes.extend(
+                with.cte_tables
+                    .iter()
+                    .map(|cte| &*cte.query as *const Query),
+            );
+        }
+        // `WITH` 定义的查询与所属的查询同层
+        let counted = !is_cte;
+        if counted {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+        }
+        self.counted.push(counted);
+        self.count_body(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        if self.counted.pop() == Some(true) {
+            self.depth -= 1;
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<Self::Break> {
+        match statement {
+            Statement::Update { table, from, .. } => {
+                let mut tables = vec![table.clone()];
+                if let Some(
+                    UpdateTableFromKind::BeforeSet(from) | UpdateTableFromKind::AfterSet(from),
+                ) = from
+                {
+                    tables.extend(from.iter().cloned());
+                }
+                self.count_from(&tables);
+            }
+            Statement::Delete(delete) => {
+                let (FromTable::WithFromKeyword(from) | FromTable::WithoutKeyword(from)) =
+                    &delete.from;
+                self.count_from(from);
+                if let Some(using) = &delete.using {
+                    self.count_from(using);
+                    self.joins += 1;
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// 文本格式输出
+pub fn print_text(report: &MetricsReport) {
+    for metrics in report.statements.iter() {
+        let values: Vec<String> = Metric::ALL
+            .iter()
+            .map(|m| format!("{}={}", m.name(), m.value(metrics)))
+            .collect();
+        println!(
+            "{}:{}: {} {}{}",
+            metrics.file,
+            metrics.line,
+            metrics.key,
+            values.join(" "),
+            if metrics.estimated {
+                " (estimated)"
+            } else {
+                ""
+            }
+        );
+    }
+    println!(
+        "statements: {} of {}",
+        report.statements.len(),
+        report.total
+    );
+}
+
+/// `csv` 格式输出
+pub fn print_csv(report: &MetricsReport) {
+    let mut header = vec!["statement", "file", "line", "kind"];
+    header.extend(Metric::ALL.iter().map(|m| m.name()));
+    header.push("estimated");
+    csv_writer::print_row(&header);
+    for metrics in report.statements.iter() {
+        let mut row = vec![
+            metrics.key.clone(),
+            metrics.file.clone(),
+            metrics.line.to_string(),
+            metrics.kind.clone(),
+        ];
+        row.extend(Metric::ALL.iter().map(|m| m.value(metrics).to_string()));
+        row.push(metrics.estimated.to_string());
+        csv_writer::print_row(&row);
+    }
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &MetricsReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize metrics report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        extract::sql_extractor,
+        xbatis::{mybatis_parser, xbatis_parser::Parser},
+    };
+    use std::collections::HashMap;
+
+    fn extract(statements: &str) -> Vec<ExtractedSql> {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        parser.setup_replace_num(10);
+        let xml = format!("<mapper namespace=\"m\">{statements}</mapper>");
+        let parsed = parser.parse_xml("test.xml", &mut xml.as_bytes());
+        let mut global_inc_map = HashMap::new();
+        let mut global_trees = HashMap::new();
+        sql_extractor::register_sql_parts(&mut global_inc_map, &mut global_trees, &parsed);
+        sql_extractor::resolve_file(&parser, "test.xml", &parsed, &global_inc_map, &global_trees)
+    }
+
+    fn measure_all(
+        statements: &str,
+        sort: Option<Metric>,
+        minimums: &[(Metric, usize)],
+    ) -> MetricsReport {
+        let sqls = extract(statements);
+        measure(
+            &sqls,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            sort,
+            minimums,
+        )
+    }
+
+    fn values(metrics: &StatementMetrics) -> Vec<usize> {
+        Metric::ALL.iter().map(|m| m.value(metrics)).collect()
+    }
+
+    #[test]
+    fn every_metric_is_measured() {
+        let sqls = extract(
+            "<sql id=\"inner\">and b.y = #{y}</sql>\
+             <sql id=\"outer\">a.x = #{x} <include refid=\"inner\"/></sql>\
+             <select id=\"q\">select a.id from a join b on a.id = b.id, c \
+             where <include refid=\"outer\"/> and a.id in (select id from d where e in (select e from f)) \
+             <if test=\"z != null\">and c.z = #{z}</if>\
+             <choose><when test=\"w\">and a.w = 1</when><otherwise>and a.w = 2</otherwise></choose> \
+             union select id from g</select>",
+        );
+        let report = measure(
+            &sqls,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            None,
+            &[],
+        );
+        let metrics = &report.statements[0];
+        assert!(!metrics.estimated);
+        assert_eq!(values(metrics), [2, 2, 3, 1, 2, sqls[0].sql.len(), 3]);
+    }
+
+    #[test]
+    fn fragments_included_twice_count_their_params_twice() {
+        let report = measure_all(
+            "<sql id=\"cond\">x = #{x}</sql>\
+             <select id=\"q\">select 1 from a where <include refid=\"cond\"/> \
+             or <include refid=\"cond\"/></select>",
+            None,
+            &[],
+        );
+        assert_eq!(report.statements[0].params, 2);
+    }
+
+    #[test]
+    fn cte_bodies_are_not_subqueries() {
+        let report = measure_all(
+            "<select id=\"a\">with x as (select id from t) select * from x</select>\
+             <select id=\"b\">with x as (select id from t where id in (select id from u)) \
+             select * from x</select>",
+            None,
+            &[],
+        );
+        let depths: Vec<usize> = report.statements.iter().map(|m| m.subquery_depth).collect();
+        assert_eq!(depths, [0, 1]);
+    }
+
+    #[test]
+    fn estimates_count_comma_joins() {
+        let report = measure_all(
+            "<select id=\"a\">with x as (select 1 from dual) \
+             select * from a, b, (select 1 from c) d where a.id = b.id and a.x in (1, 2) \
+             connect by prior a.id = b.id</select>",
+            None,
+            &[],
+        );
+        let metrics = &report.statements[0];
+        assert!(metrics.estimated);
+        assert_eq!((metrics.joins, metrics.subquery_depth), (2, 1));
+    }
+
+    #[test]
+    fn minimums_filter_and_sort_orders() {
+        let statements = "<select id=\"a\">select 1 from a join b on a.id = b.id</select>\
+             <select id=\"b\">select 1 from a where x in (select x from b)</select>\
+             <select id=\"c\">select 1 from a, b, c</select>\
+             <select id=\"d\">select 1 from a</select>";
+        let keys = |report: &MetricsReport| -> Vec<String> {
+            report.statements.iter().map(|m| m.key.clone()).collect()
+        };
+        let report = measure_all(statements, Some(Metric::Joins), &[]);
+        assert_eq!(report.total, 4);
+        assert_eq!(keys(&report), ["m.c", "m.a", "m.b", "m.d"]);
+        let minimums = [(Metric::Joins, 2), (Metric::SubqueryDepth, 1)];
+        let report = measure_all(statements, None, &minimums);
+        assert_eq!(keys(&report), ["m.b", "m.c"]);
+    }
+}
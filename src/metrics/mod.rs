@@ -0,0 +1,2 @@
+/// 语句复杂度指标
+pub mod complexity_metrics;
@@ -0,0 +1,14 @@
+/// `csv` 字段，含逗号、引号或换行时加引号
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        String::from(value)
+    }
+}
+
+/// 输出一行到 `stdout`
+pub fn print_row<S: AsRef<str>>(fields: &[S]) {
+    let row: Vec<String> = fields.iter().map(|f| field(f.as_ref())).collect();
+    println!("{}", row.join(","));
+}
@@ -1,2 +1,4 @@
+/// `csv` 输出
+pub mod csv_writer;
 /// 保存
 pub mod sql_saver;
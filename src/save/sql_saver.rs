@@ -92,9 +92,11 @@ pub(crate) fn rewrite(
         let mut wf = File::create(&wrapped.path).unwrap();
         let buf_reader = BufReader::new(&rf);
         let mut buf_writer = BufWriter::new(&mut wf);
+        let mut query_no = 0;
         buf_reader.lines().for_each(|rs| {
             let line = rs.unwrap_or_else(|_| "".to_string());
-            let new_line = parser.replace_final_sql(arc_global_inc_map.clone(), &line);
+            let new_line =
+                parser.replace_final_sql(arc_global_inc_map.clone(), &line, &mut query_no);
             let _ = &buf_writer.write_all(new_line.as_bytes()).unwrap();
             let _ = &buf_writer.write_all(&CRLF).unwrap();
         });
@@ -105,3 +107,23 @@ pub(crate) fn rewrite(
     drop_global_var::<Mutex<WrappedFile>>("output_file");
     info!("rewrite result.sql done");
 }
+
+/// 一次性写入 `result.sql`，先写临时文件再改名，供监听模式反复重写
+pub(crate) fn write_result(output_dir: &String, sql_stores: &[Vec<String>]) {
+    let tmp = PathBuf::from(format!("{}/{}", output_dir, "result.tmp"));
+    let path = PathBuf::from(format!("{}/{}", output_dir, "result.sql"));
+    let mut wf = File::create(&tmp).unwrap_or_else(|e| {
+        warn!("try to write sql to {output_dir:?} failed: {e}");
+        process::exit(-1);
+    });
+    for sql_store in sql_stores.iter().filter(|s| !s.is_empty()) {
+        for sql in sql_store {
+            write2file(&mut wf, sql.as_bytes());
+            write2file(&mut wf, &CRLF);
+        }
+        write2file(&mut wf, &CRLF);
+    }
+    if wf.sync_all().is_err() || fs::rename(&tmp, &path).is_err() {
+        warn!("try to rewrite {path:?} failed");
+    }
+}
@@ -1,7 +1,35 @@
 use log::{debug, warn};
-use std::{path::Path, process};
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufRead, BufReader},
+    path::Path,
+    process,
+};
 use walkdir::{DirEntry, Error, WalkDir};
 
+/// 收集所有来源：多个目录，以及文件清单。重复的文件只保留首次出现的那个
+pub fn collect(files: &mut Vec<String>, dirs: &[String], files_from: &Option<String>) {
+    let mut collected: Vec<String> = Vec::new();
+    for dir in dirs {
+        scan(&mut collected, dir);
+    }
+    if let Some(list) = files_from {
+        read_list(&mut collected, list);
+    }
+    let mut seen = HashSet::new();
+    for file in collected {
+        let key = fs::canonicalize(&file)
+            .map(|p| p.to_string_lossy().to_string())
+            .unwrap_or_else(|_| file.clone());
+        if seen.insert(key) {
+            files.push(file);
+        } else {
+            debug!("skip duplicated file: {file}");
+        }
+    }
+}
+
 /// 扫描给定的文件夹，过滤出 `xml` 文件
 pub fn scan(files: &mut Vec<String>, dir: &String) {
     for d in WalkDir::new(dir).into_iter() {
@@ -9,6 +37,34 @@ pub fn scan(files: &mut Vec<String>, dir: &String) {
     }
 }
 
+/// 读取文件清单，每行一个文件，忽略空行与 `#` 开头的行。`-` 表示从 `stdin` 读取
+fn read_list(files: &mut Vec<String>, list: &str) {
+    let reader: Box<dyn BufRead> = if list == "-" {
+        Box::new(BufReader::new(io::stdin()))
+    } else {
+        let f = File::open(list).unwrap_or_else(|e| {
+            warn!("open file list [{list}] failed: {e}");
+            process::exit(-1);
+        });
+        Box::new(BufReader::new(f))
+    };
+    for line in reader.lines() {
+        let line = line.unwrap_or_else(|e| {
+            warn!("read file list [{list}] failed: {e}");
+            process::exit(-1);
+        });
+        let file = line.trim();
+        if file.is_empty() || file.starts_with('#') {
+            continue;
+        }
+        if Path::new(file).is_file() {
+            files.push(file.to_string());
+        } else {
+            warn!("file [{file}] in list [{list}] not found, skip it");
+        }
+    }
+}
+
 /// 判断是否是 `xml` 文件。是，攒入 `files`。
 fn check_if_xml_file(files: &mut Vec<String>, d: Result<DirEntry, Error>) {
     if d.is_err() {
@@ -30,3 +86,67 @@ fn check_if_xml_file(files: &mut Vec<String>, d: Result<DirEntry, Error>) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// 测试用的临时目录，按名称区分，重复运行时先清空
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("xbatis2sql-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn touch(path: &Path) -> String {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, "<mapper/>").unwrap();
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn collects_xml_from_dirs_and_list_once() {
+        let dir = temp_dir("collect");
+        let a = touch(&dir.join("a/one.xml"));
+        let b = touch(&dir.join("b/two.xml"));
+        touch(&dir.join("b/readme.txt"));
+        let extra = touch(&dir.join("c/three.xml"));
+        let list = dir.join("list.txt");
+        fs::write(
+            &list,
+            format!(
+                "# comment\n\n{a}\n{extra}\n{}\n",
+                dir.join("missing.xml").display()
+            ),
+        )
+        .unwrap();
+        let mut files = Vec::new();
+        collect(
+            &mut files,
+            &[
+                dir.join("a").to_string_lossy().to_string(),
+                dir.join("b").to_string_lossy().to_string(),
+            ],
+            &Some(list.to_string_lossy().to_string()),
+        );
+        assert_eq!(files, [a, b, extra]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn overlapping_dirs_are_deduplicated() {
+        let dir = temp_dir("overlap");
+        let a = touch(&dir.join("sub/one.xml"));
+        let root = dir.to_string_lossy().to_string();
+        let mut files = Vec::new();
+        collect(
+            &mut files,
+            &[root, dir.join("sub").to_string_lossy().to_string()],
+            &None,
+        );
+        assert_eq!(files, [a]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,368 @@
+use super::lexer::{self, TemplateSyntax, Token, TokenKind};
+use crate::xbatis::def::DialectType;
+
+/// 子查询缩进
+const INDENT: usize = 4;
+/// `AND`/`OR` 续行缩进
+const CONTINUATION: usize = 2;
+
+/// 子句关键字，长的在前，按顺序匹配
+const CLAUSES: [&[&str]; 36] = [
+    &["ON", "DUPLICATE", "KEY", "UPDATE"],
+    &["LEFT", "OUTER", "JOIN"],
+    &["RIGHT", "OUTER", "JOIN"],
+    &["FULL", "OUTER", "JOIN"],
+    &["WHEN", "NOT", "MATCHED"],
+    &["LEFT", "JOIN"],
+    &["RIGHT", "JOIN"],
+    &["FULL", "JOIN"],
+    &["INNER", "JOIN"],
+    &["CROSS", "JOIN"],
+    &["NATURAL", "JOIN"],
+    &["WHEN", "MATCHED"],
+    &["INSERT", "INTO"],
+    &["DELETE", "FROM"],
+    &["MERGE", "INTO"],
+    &["GROUP", "BY"],
+    &["ORDER", "BY"],
+    &["CONNECT", "BY"],
+    &["START", "WITH"],
+    &["UNION", "ALL"],
+    &["FOR", "UPDATE"],
+    &["FETCH", "FIRST"],
+    &["FETCH", "NEXT"],
+    &["JOIN"],
+    &["SELECT"],
+    &["FROM"],
+    &["WHERE"],
+    &["HAVING"],
+    &["UNION"],
+    &["INTERSECT"],
+    &["MINUS"],
+    &["EXCEPT"],
+    &["LIMIT"],
+    &["VALUES"],
+    &["UPDATE"],
+    &["SET"],
+];
+
+/// 子句种类，决定逗号与 `AND`/`OR` 是否换行
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Clause {
+    None,
+    /// 逗号换行并与首项对齐
+    List,
+    /// `AND`/`OR` 换行
+    Condition,
+    Insert,
+    Other,
+}
+
+fn clause_of(words: &[&str]) -> Clause {
+    match words {
+        ["SELECT"] | ["SET"] => Clause::List,
+        ["WHERE"] | ["HAVING"] | ["CONNECT", "BY"] | ["START", "WITH"] => Clause::Condition,
+        [.., "JOIN"] => Clause::Condition,
+        ["INSERT", "INTO"] => Clause::Insert,
+        _ => Clause::Other,
+    }
+}
+
+/// 嵌套层次，每个子查询一层
+struct Frame {
+    indent: usize,
+    /// 子查询右括号的缩进，与左括号所在行对齐
+    close_indent: usize,
+    clause: Clause,
+    /// 列表项对齐的列
+    align: usize,
+    /// 层内非子查询括号的深度，函数调用与 `IN (...)` 内不换行
+    parens: usize,
+    /// 是否在 `BETWEEN ... AND` 之间
+    in_between: bool,
+}
+
+impl Frame {
+    fn new(indent: usize, close_indent: usize) -> Self {
+        Frame {
+            indent,
+            close_indent,
+            clause: Clause::None,
+            align: indent,
+            parens: 0,
+            in_between: false,
+        }
+    }
+}
+
+/// 多行格式化：每个子句一行，`SELECT`/`SET` 列表对齐，子查询逐层缩进。
+/// 输入为规整后的单行 `sql`；执行计划前缀单独成行，不拆开。
+pub fn format(sql: &str, dialect: &DialectType, syntax: TemplateSyntax) -> String {
+    let tokens = lexer::tokenize(sql, dialect, syntax);
+    let mut f = Formatter {
+        out: String::new(),
+        line_start: 0,
+        frames: vec![Frame::new(0, 0)],
+        syntax,
+    };
+    let mut i = explain_prefix_len(&tokens);
+    for (n, token) in tokens[..i].iter().enumerate() {
+        f.write(token, n > 0 && token.space_before);
+    }
+    while i < tokens.len() {
+        i += f.push(&tokens, i);
+    }
+    f.out
+}
+
+struct Formatter {
+    out: String,
+    /// 当前行在 `out` 中的起点，用于计算列
+    line_start: usize,
+    frames: Vec<Frame>,
+    /// 还原占位符的写法
+    syntax: TemplateSyntax,
+}
+
+impl Formatter {
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().unwrap()
+    }
+
+    /// 处理第 `i` 个词，返回消费的词数
+    fn push(&mut self, tokens: &[Token], i: usize) -> usize {
+        let token = &tokens[i];
+        if self.frame().parens == 0 {
+            if let Some(words) = match_clause(tokens, i) {
+                let clause = clause_of(words);
+                if words != ["VALUES"] || self.frame().clause == Clause::Insert {
+                    return self.start_clause(tokens, i, words.len(), clause);
+                }
+            }
+            if (token.is_word("AND") || token.is_word("OR"))
+                && self.frame().clause == Clause::Condition
+            {
+                if token.is_word("AND") && self.frame().in_between {
+                    self.frame().in_between = false;
+                } else {
+                    let indent = self.frame().indent + CONTINUATION;
+                    self.newline(indent);
+                    self.write(token, false);
+                    return 1;
+                }
+            }
+            if token.is_word("BETWEEN") {
+                self.frame().in_between = true;
+            }
+            if token.is_symbol(",") && self.frame().clause == Clause::List {
+                self.write(token, false);
+                let align = self.frame().align;
+                self.newline(align);
+                return 1;
+            }
+        }
+        if token.is_symbol("(") {
+            self.write(token, token.space_before);
+            if is_subquery(tokens, i + 1) {
+                let base = self.leading_spaces();
+                self.frames.push(Frame::new(base + INDENT, base));
+            } else {
+                self.frame().parens += 1;
+            }
+            return 1;
+        }
+        if token.is_symbol(")") {
+            if self.frame().parens > 0 {
+                self.frame().parens -= 1;
+            } else if self.frames.len() > 1 {
+                let indent = self.frame().close_indent;
+                self.frames.pop();
+                self.newline(indent);
+                self.write(token, false);
+                return 1;
+            }
+        }
+        self.write(token, token.space_before);
+        1
+    }
+
+    fn start_clause(&mut self, tokens: &[Token], i: usize, len: usize, clause: Clause) -> usize {
+        let indent = self.frame().indent;
+        if !self.at_line_start() {
+            self.newline(indent);
+        }
+        for (n, token) in tokens[i..i + len].iter().enumerate() {
+            self.write(token, n > 0);
+        }
+        let align = self.column() + 1;
+        let frame = self.frame();
+        frame.clause = clause;
+        frame.align = align;
+        frame.in_between = false;
+        len
+    }
+
+    fn write(&mut self, token: &Token, space: bool) {
+        if space && !self.at_line_start() {
+            self.out.push(' ');
+        }
+        match token.kind {
+            TokenKind::Comment => {
+                self.out.push_str("/* ");
+                self.out.push_str(&token.text);
+                self.out.push_str(" */");
+            }
+            TokenKind::Placeholder => match self.syntax {
+                TemplateSyntax::IBatis => self.out.push_str(&format!("#{}#", token.text)),
+                TemplateSyntax::MyBatis => self.out.push_str(&format!("#{{{}}}", token.text)),
+            },
+            TokenKind::Schema => self.out.push_str(&format!("${{{}}}", token.text)),
+            _ => self.out.push_str(&token.text),
+        }
+    }
+
+    fn newline(&mut self, indent: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+        while self.out.ends_with(' ') {
+            self.out.pop();
+        }
+        self.out.push('\n');
+        self.line_start = self.out.len();
+        self.out.push_str(&" ".repeat(indent));
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out[self.line_start..].trim().is_empty()
+    }
+
+    fn leading_spaces(&self) -> usize {
+        self.out[self.line_start..]
+            .chars()
+            .take_while(|c| *c == ' ')
+            .count()
+    }
+
+    fn column(&self) -> usize {
+        self.out[self.line_start..].chars().count()
+    }
+}
+
+/// 从第 `i` 个词开始匹配子句关键字
+fn match_clause(tokens: &[Token], i: usize) -> Option<&'static [&'static str]> {
+    CLAUSES.iter().copied().find(|words| {
+        words
+            .iter()
+            .enumerate()
+            .all(|(n, w)| tokens.get(i + n).is_some_and(|t| t.is_word(w)))
+    })
+}
+
+/// 执行计划前缀的词数：`EXPLAIN PLAN ... FOR` 到 `FOR` 为止，其余到语句的首个关键字为止
+fn explain_prefix_len(tokens: &[Token]) -> usize {
+    if !tokens.first().is_some_and(|t| t.is_word("EXPLAIN")) {
+        return 0;
+    }
+    if tokens.get(1).is_some_and(|t| t.is_word("PLAN")) {
+        return tokens
+            .iter()
+            .position(|t| t.is_word("FOR"))
+            .map_or(0, |i| i + 1);
+    }
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(1) {
+        if token.is_symbol("(") {
+            depth += 1;
+        } else if token.is_symbol(")") {
+            depth -= 1;
+        } else if depth == 0
+            && [
+                "SELECT", "WITH", "INSERT", "UPDATE", "DELETE", "MERGE", "REPLACE",
+            ]
+            .iter()
+            .any(|w| token.is_word(w))
+        {
+            return i;
+        }
+    }
+    0
+}
+
+/// 括号后是否紧跟子查询
+fn is_subquery(tokens: &[Token], i: usize) -> bool {
+    tokens
+        .iter()
+        .skip(i)
+        .find(|t| t.kind != TokenKind::Comment)
+        .is_some_and(|t| t.is_word("SELECT") || t.is_word("WITH"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_clause_per_line() {
+        let sql = format(
+            "SELECT a, b FROM t LEFT JOIN u ON u.id = t.uid WHERE a = 1 AND b = 2 ORDER BY a;",
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(
+            sql,
+            "SELECT a,\n       b\nFROM t\nLEFT JOIN u ON u.id = t.uid\nWHERE a = 1\n  AND b = 2\nORDER BY a;"
+        );
+    }
+
+    #[test]
+    fn subquery_is_indented() {
+        let sql = format(
+            "SELECT a FROM t WHERE id IN (SELECT id FROM u WHERE x = @1) AND f(a, b) = 1;",
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+        );
+        // 函数调用内的逗号不换行
+        assert_eq!(
+            sql,
+            "SELECT a\nFROM t\nWHERE id IN (\n    SELECT id\n    FROM u\n    WHERE x = @1\n)\n  AND f(a, b) = 1;"
+        );
+    }
+
+    #[test]
+    fn literals_and_placeholders_are_kept() {
+        let sql = format(
+            "SELECT 'a, b' FROM t WHERE x = $1",
+            &DialectType::Kingbase,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(sql, "SELECT 'a, b'\nFROM t\nWHERE x = $1");
+    }
+
+    #[test]
+    fn explain_prefix_stays_on_one_line() {
+        let format = |sql| format(sql, &DialectType::Oracle, TemplateSyntax::MyBatis);
+        assert_eq!(
+            format("EXPLAIN PLAN SET STATEMENT_ID = 'a1' FOR UPDATE t SET a = 1 WHERE b = 2"),
+            "EXPLAIN PLAN SET STATEMENT_ID = 'a1' FOR\nUPDATE t\nSET a = 1\nWHERE b = 2"
+        );
+        assert_eq!(
+            format("EXPLAIN PLAN SET QUERYNO = 1 FOR SELECT a FROM t"),
+            "EXPLAIN PLAN SET QUERYNO = 1 FOR\nSELECT a\nFROM t"
+        );
+        assert_eq!(
+            format("EXPLAIN (FORMAT JSON) SELECT a FROM t"),
+            "EXPLAIN (FORMAT JSON)\nSELECT a\nFROM t"
+        );
+    }
+
+    #[test]
+    fn ibatis_placeholders_are_not_split() {
+        let sql = format(
+            "SELECT a FROM t WHERE x = #x# AND y IN (#list#)",
+            &DialectType::MySQL,
+            TemplateSyntax::IBatis,
+        );
+        assert_eq!(sql, "SELECT a\nFROM t\nWHERE x = #x#\n  AND y IN (#list#)");
+    }
+}
@@ -0,0 +1,145 @@
+/// 关键字表，按字典序排列，供二分查找；函数名与类型名不在其中
+const KEYWORDS: [&str; 121] = [
+    "ADD",
+    "ALL",
+    "ALTER",
+    "ANALYZE",
+    "AND",
+    "ANY",
+    "AS",
+    "ASC",
+    "BEGIN",
+    "BETWEEN",
+    "BY",
+    "CASCADE",
+    "CASE",
+    "CHECK",
+    "COLUMN",
+    "COMMENT",
+    "COMMIT",
+    "CONNECT",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "DECLARE",
+    "DEFAULT",
+    "DELETE",
+    "DESC",
+    "DISTINCT",
+    "DROP",
+    "DUPLICATE",
+    "ELSE",
+    "END",
+    "ESCAPE",
+    "EXCEPT",
+    "EXEC",
+    "EXECUTE",
+    "EXISTS",
+    "EXPLAIN",
+    "FALSE",
+    "FETCH",
+    "FIRST",
+    "FOR",
+    "FOREIGN",
+    "FORMAT",
+    "FROM",
+    "FULL",
+    "GRANT",
+    "GROUP",
+    "HAVING",
+    "IF",
+    "IGNORE",
+    "IN",
+    "INDEX",
+    "INNER",
+    "INSERT",
+    "INTERSECT",
+    "INTERVAL",
+    "INTO",
+    "IS",
+    "JOIN",
+    "KEY",
+    "LAST",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "LOCK",
+    "MATCHED",
+    "MERGE",
+    "MINUS",
+    "NATURAL",
+    "NEXT",
+    "NOT",
+    "NOWAIT",
+    "NULL",
+    "NULLS",
+    "OF",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PLAN",
+    "PRIMARY",
+    "PRIOR",
+    "QUERY",
+    "RECURSIVE",
+    "REFERENCES",
+    "REGEXP",
+    "REPLACE",
+    "RETURNING",
+    "REVOKE",
+    "RIGHT",
+    "ROLLBACK",
+    "ROWS",
+    "SELECT",
+    "SET",
+    "SHARE",
+    "SKIP",
+    "START",
+    "TABLE",
+    "THEN",
+    "TO",
+    "TRUE",
+    "TRUNCATE",
+    "UNION",
+    "UNIQUE",
+    "UNKNOWN",
+    "UPDATE",
+    "USING",
+    "VALUES",
+    "VIEW",
+    "WAIT",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+    "WITHIN",
+    "WITHOUT",
+    "XOR",
+    "ZONE",
+];
+
+/// 是否为关键字，忽略大小写
+pub fn is_keyword(word: &str) -> bool {
+    KEYWORDS
+        .binary_search(&word.to_ascii_uppercase().as_str())
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keywords_are_sorted_for_binary_search() {
+        assert!(KEYWORDS.windows(2).all(|w| w[0] < w[1]));
+        assert!(is_keyword("select"));
+        assert!(is_keyword("Where"));
+        assert!(!is_keyword("count"));
+        assert!(!is_keyword("users"));
+    }
+}
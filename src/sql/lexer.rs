@@ -0,0 +1,405 @@
+use crate::xbatis::def::DialectType;
+
+/// 模板语法，决定 `#`、`$` 的含义
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateSyntax {
+    /// `#name#`、`$name$`，`${schema}` 视为 `schema`
+    IBatis,
+    /// `#{name}`、`${name}`，`${schema}.` 视为 `schema`
+    MyBatis,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenKind {
+    /// 关键字或未加引号的标识符
+    Word,
+    /// 加引号的标识符，`"name"`、`` `name` ``
+    QuotedIdent,
+    /// 字符串字面量
+    StringLit,
+    /// 数字
+    Number,
+    /// 参数占位，`#{...}`、`#...#` 等
+    Placeholder,
+    /// `schema` 替代符
+    Schema,
+    /// 注释，行注释会被转为块注释
+    Comment,
+    /// 优化器提示，`/*+ ... */`
+    Hint,
+    /// 其余符号
+    Symbol,
+}
+
+#[derive(Debug, Clone)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// 原文，占位符为括号内的表达式，如 `id,jdbcType=VARCHAR`
+    pub text: String,
+    /// 前面是否有空白，规整时折叠为一个空格
+    pub space_before: bool,
+}
+
+impl Token {
+    fn new(kind: TokenKind, text: String, space_before: bool) -> Self {
+        Token {
+            kind,
+            text,
+            space_before,
+        }
+    }
+
+    /// 是否为指定关键字，忽略大小写
+    pub fn is_word(&self, word: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(word)
+    }
+
+    pub fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+}
+
+/// 按方言与模板语法切分 `sql`
+pub fn tokenize(sql: &str, dialect: &DialectType, syntax: TemplateSyntax) -> Vec<Token> {
+    Lexer {
+        chars: sql.chars().collect(),
+        pos: 0,
+        dialect,
+        syntax,
+    }
+    .run()
+}
+
+struct Lexer<'a> {
+    chars: Vec<char>,
+    pos: usize,
+    dialect: &'a DialectType,
+    syntax: TemplateSyntax,
+}
+
+impl Lexer<'_> {
+    fn run(mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        let mut space_before = false;
+        while let Some(c) = self.peek(0) {
+            if c.is_whitespace() {
+                space_before = true;
+                self.pos += 1;
+                continue;
+            }
+            let token = self.next_token(c, space_before);
+            space_before = false;
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    fn next_token(&mut self, c: char, space: bool) -> Token {
+        let start = self.pos;
+        match c {
+            '-' if self.peek(1) == Some('-') => {
+                self.pos += 2;
+                let text = self.take_until_newline();
+                Token::new(TokenKind::Comment, text.trim().to_string(), space)
+            }
+            '/' if self.peek(1) == Some('*') => self.block_comment(space),
+            '#' => self.hash(space),
+            '$' => self.dollar(space),
+            '\'' => {
+                self.quoted('\'', self.is_mysql());
+                Token::new(TokenKind::StringLit, self.text_from(start), space)
+            }
+            '"' => {
+                self.quoted('"', false);
+                Token::new(TokenKind::QuotedIdent, self.text_from(start), space)
+            }
+            '`' if self.is_mysql() || matches!(self.dialect, DialectType::Sqlite) => {
+                self.quoted('`', false);
+                Token::new(TokenKind::QuotedIdent, self.text_from(start), space)
+            }
+            'q' | 'Q' if self.is_oracle() && self.peek(1) == Some('\'') => {
+                self.oracle_q_quote();
+                Token::new(TokenKind::StringLit, self.text_from(start), space)
+            }
+            // 只取数字与小数点，`1AND` 这样紧跟的关键字单独成词
+            c if c.is_ascii_digit() => {
+                self.take_while(|c| c.is_ascii_digit() || c == '.');
+                Token::new(TokenKind::Number, self.text_from(start), space)
+            }
+            c if is_word_char(c) => {
+                self.take_while(is_word_char);
+                Token::new(TokenKind::Word, self.text_from(start), space)
+            }
+            _ => {
+                self.pos += 1;
+                Token::new(TokenKind::Symbol, c.to_string(), space)
+            }
+        }
+    }
+
+    fn block_comment(&mut self, space: bool) -> Token {
+        let start = self.pos;
+        self.pos += 2;
+        while self.pos < self.chars.len() {
+            if self.peek(0) == Some('*') && self.peek(1) == Some('/') {
+                self.pos += 2;
+                break;
+            }
+            self.pos += 1;
+        }
+        let text = self.text_from(start);
+        if text.starts_with("/*+") {
+            Token::new(TokenKind::Hint, text, space)
+        } else {
+            let body = text.trim_start_matches("/*").trim_end_matches("*/");
+            Token::new(TokenKind::Comment, body.trim().to_string(), space)
+        }
+    }
+
+    fn hash(&mut self, space: bool) -> Token {
+        if self.peek(1) == Some('{') {
+            if let Some(expr) = self.braced() {
+                return Token::new(TokenKind::Placeholder, expr, space);
+            }
+        } else if self.syntax == TemplateSyntax::IBatis {
+            if let Some(expr) = self.delimited('#') {
+                return Token::new(TokenKind::Placeholder, expr, space);
+            }
+        } else if self.is_mysql() {
+            self.pos += 1;
+            let text = self.take_until_newline();
+            return Token::new(TokenKind::Comment, text.trim().to_string(), space);
+        }
+        self.pos += 1;
+        Token::new(TokenKind::Symbol, String::from("#"), space)
+    }
+
+    fn dollar(&mut self, space: bool) -> Token {
+        if self.peek(1) == Some('{') {
+            if let Some(expr) = self.braced() {
+                let schema = match self.syntax {
+                    TemplateSyntax::IBatis => true,
+                    TemplateSyntax::MyBatis => self.peek(0) == Some('.'),
+                };
+                if schema {
+                    return Token::new(TokenKind::Schema, expr, space);
+                }
+                return Token::new(TokenKind::Placeholder, expr, space);
+            }
+        } else if self.syntax == TemplateSyntax::IBatis {
+            if let Some(expr) = self.delimited('$') {
+                return Token::new(TokenKind::Placeholder, expr, space);
+            }
+        }
+        self.pos += 1;
+        Token::new(TokenKind::Symbol, String::from("$"), space)
+    }
+
+    /// `#{...}`/`${...}`，返回括号内的表达式，未闭合时不消费
+    fn braced(&mut self) -> Option<String> {
+        let end = (self.pos + 2..self.chars.len()).find(|i| self.chars[*i] == '}')?;
+        let expr: String = self.chars[self.pos + 2..end].iter().collect();
+        self.pos = end + 1;
+        Some(expr.trim().to_string())
+    }
+
+    /// `#...#`/`$...$`，返回两个分隔符之间的表达式，未闭合时不消费；
+    /// 属性名不以数字开头，`$1` 这样的编号占位符不在此列
+    fn delimited(&mut self, delimiter: char) -> Option<String> {
+        if self.peek(1).is_none_or(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        let end = (self.pos + 1..self.chars.len()).find(|i| self.chars[*i] == delimiter)?;
+        if end == self.pos + 1 {
+            return None;
+        }
+        let expr: String = self.chars[self.pos + 1..end].iter().collect();
+        self.pos = end + 1;
+        Some(expr.trim().to_string())
+    }
+
+    /// 引号内容，重复引号视为转义；`backslash` 为真时 `\` 亦为转义
+    fn quoted(&mut self, quote: char, backslash: bool) {
+        self.pos += 1;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            if backslash && c == '\\' {
+                self.pos += 1;
+            } else if c == quote {
+                if self.peek(0) == Some(quote) {
+                    self.pos += 1;
+                } else {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// `Oracle` 的 `q'[...]'`
+    fn oracle_q_quote(&mut self) {
+        self.pos += 2;
+        let open = match self.peek(0) {
+            Some(c) => c,
+            None => return,
+        };
+        let close = match open {
+            '[' => ']',
+            '(' => ')',
+            '{' => '}',
+            '<' => '>',
+            c => c,
+        };
+        self.pos += 1;
+        while let Some(c) = self.peek(0) {
+            self.pos += 1;
+            if c == close && self.peek(0) == Some('\'') {
+                self.pos += 1;
+                break;
+            }
+        }
+    }
+
+    fn take_until_newline(&mut self) -> String {
+        let start = self.pos;
+        self.take_while(|c| c != '\n');
+        self.text_from(start)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) {
+        while self.peek(0).is_some_and(&f) {
+            self.pos += 1;
+        }
+    }
+
+    fn text_from(&self, start: usize) -> String {
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn peek(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn is_mysql(&self) -> bool {
+        matches!(
+            self.dialect,
+            DialectType::MySQL | DialectType::OceanBaseMySql
+        )
+    }
+
+    fn is_oracle(&self) -> bool {
+        matches!(
+            self.dialect,
+            DialectType::Oracle | DialectType::OceanBaseOracle
+        )
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str, dialect: &DialectType, syntax: TemplateSyntax) -> Vec<(TokenKind, String)> {
+        tokenize(sql, dialect, syntax)
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect()
+    }
+
+    #[test]
+    fn placeholder_followed_by_keyword() {
+        let tokens = kinds(
+            "a = #{a}AND b",
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(tokens[2], (TokenKind::Placeholder, String::from("a")));
+        assert_eq!(tokens[3], (TokenKind::Word, String::from("AND")));
+    }
+
+    #[test]
+    fn ibatis_placeholders() {
+        let tokens = kinds(
+            "#id# || $col$",
+            &DialectType::Oracle,
+            TemplateSyntax::IBatis,
+        );
+        assert_eq!(tokens[0], (TokenKind::Placeholder, String::from("id")));
+        assert_eq!(tokens[3], (TokenKind::Placeholder, String::from("col")));
+        // `$1` 是编号占位符，不是属性名
+        let tokens = kinds("$1", &DialectType::Kingbase, TemplateSyntax::IBatis);
+        assert_eq!(tokens[0], (TokenKind::Symbol, String::from("$")));
+    }
+
+    #[test]
+    fn backtick_only_quotes_in_mysql_and_sqlite() {
+        let mysql = kinds("`order`", &DialectType::MySQL, TemplateSyntax::MyBatis);
+        assert_eq!(
+            mysql,
+            vec![(TokenKind::QuotedIdent, String::from("`order`"))]
+        );
+        let oracle = kinds("`order`", &DialectType::Oracle, TemplateSyntax::MyBatis);
+        assert_eq!(oracle[0], (TokenKind::Symbol, String::from("`")));
+    }
+
+    #[test]
+    fn oceanbase_modes_follow_their_base_dialect() {
+        let mysql = kinds(
+            r"`order` 'a\'b'",
+            &DialectType::OceanBaseMySql,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(mysql[0], (TokenKind::QuotedIdent, String::from("`order`")));
+        assert_eq!(mysql[1], (TokenKind::StringLit, String::from(r"'a\'b'")));
+        let oracle = kinds(
+            "q'[it's]'",
+            &DialectType::OceanBaseOracle,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(
+            oracle,
+            vec![(TokenKind::StringLit, String::from("q'[it's]'"))]
+        );
+    }
+
+    #[test]
+    fn backslash_escapes_only_in_mysql() {
+        let mysql = kinds(r"'a\'b' x", &DialectType::MySQL, TemplateSyntax::MyBatis);
+        assert_eq!(mysql[0], (TokenKind::StringLit, String::from(r"'a\'b'")));
+        let oracle = kinds(r"'a\' x", &DialectType::Oracle, TemplateSyntax::MyBatis);
+        assert_eq!(oracle[0], (TokenKind::StringLit, String::from(r"'a\'")));
+    }
+
+    #[test]
+    fn doubled_and_oracle_q_quotes() {
+        let tokens = kinds(
+            "'it''s' q'[it's]'",
+            &DialectType::Oracle,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(tokens[0], (TokenKind::StringLit, String::from("'it''s'")));
+        assert_eq!(tokens[1], (TokenKind::StringLit, String::from("q'[it's]'")));
+    }
+
+    #[test]
+    fn hash_comment_only_in_mysql() {
+        let mysql = kinds("a # note\nb", &DialectType::MySQL, TemplateSyntax::MyBatis);
+        assert_eq!(mysql[1], (TokenKind::Comment, String::from("note")));
+        let h2 = kinds("a # b", &DialectType::H2, TemplateSyntax::MyBatis);
+        assert_eq!(h2[1], (TokenKind::Symbol, String::from("#")));
+    }
+
+    #[test]
+    fn hint_is_kept_apart_from_comment() {
+        let tokens = kinds(
+            "/*+ INDEX(t) */ /* c */",
+            &DialectType::Oracle,
+            TemplateSyntax::MyBatis,
+        );
+        assert_eq!(tokens[0].0, TokenKind::Hint);
+        assert_eq!(tokens[1], (TokenKind::Comment, String::from("c")));
+    }
+}
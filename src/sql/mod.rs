@@ -0,0 +1,12 @@
+/// 多行格式化
+pub mod formatter;
+/// 关键字表
+pub mod keywords;
+/// 词法分析
+pub mod lexer;
+/// 规整
+pub mod normalizer;
+/// 变量赋值的前导语句
+pub mod prologue;
+/// 占位符样例值
+pub mod sample;
@@ -0,0 +1,544 @@
+use super::{
+    keywords,
+    lexer::{self, TemplateSyntax, Token, TokenKind},
+    prologue::{self, Bind},
+    sample::{SampleKind, SampleValues},
+};
+use crate::xbatis::{def::DialectType, xbatis_parser::var_placeholder};
+
+/// `schema` 替代符
+const SCHEMA_PLACEHOLDER: &str = "__REPLACE_SCHEMA__";
+
+/// 大小写策略，只作用于关键字；标识符、字面量与加引号的名称保持原样
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CasePolicy {
+    /// 关键字转大写
+    #[default]
+    UpperKeywords,
+    /// 保持原样
+    Preserve,
+    /// 关键字转小写
+    Lower,
+}
+
+impl CasePolicy {
+    pub fn from(name: &str) -> Option<Self> {
+        match name {
+            "upper-keywords" => Some(CasePolicy::UpperKeywords),
+            "preserve" => Some(CasePolicy::Preserve),
+            "lower" => Some(CasePolicy::Lower),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, word: &str) -> String {
+        match self {
+            CasePolicy::UpperKeywords if keywords::is_keyword(word) => word.to_ascii_uppercase(),
+            CasePolicy::Lower if keywords::is_keyword(word) => word.to_ascii_lowercase(),
+            _ => String::from(word),
+        }
+    }
+}
+
+/// 占位符样式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PlaceholderStyle {
+    /// 方言默认，见 `var_placeholder`
+    #[default]
+    Dialect,
+    /// `JDBC` 的 `?`
+    Question,
+    /// `:name`，保留参数名
+    Named,
+    /// `$1`、`$2`……，每条语句从 `1` 开始编号
+    Numbered,
+    /// `@name`，`MySQL` 会话变量
+    Variable,
+}
+
+impl PlaceholderStyle {
+    pub fn from(name: &str) -> Option<Self> {
+        match name {
+            "dialect" => Some(PlaceholderStyle::Dialect),
+            "?" => Some(PlaceholderStyle::Question),
+            ":name" => Some(PlaceholderStyle::Named),
+            "$n" => Some(PlaceholderStyle::Numbered),
+            "@name" => Some(PlaceholderStyle::Variable),
+            _ => None,
+        }
+    }
+
+    /// 第 `seq` 个占位符，`expr` 为占位符内的表达式
+    fn render(
+        &self,
+        expr: &str,
+        syntax: TemplateSyntax,
+        dialect: &DialectType,
+        seq: usize,
+    ) -> String {
+        match self {
+            PlaceholderStyle::Dialect => String::from(var_placeholder(dialect)),
+            PlaceholderStyle::Question => String::from("?"),
+            PlaceholderStyle::Named => format!(":{}", param_name(expr, syntax)),
+            PlaceholderStyle::Numbered => format!("${seq}"),
+            PlaceholderStyle::Variable => format!("@{}", param_name(expr, syntax)),
+        }
+    }
+}
+
+/// 占位符的参数名，去掉 `jdbcType` 等属性，非标识符字符换成 `_`
+fn param_name(expr: &str, syntax: TemplateSyntax) -> String {
+    let name = expr.split(',').next().unwrap_or(expr);
+    let name = match syntax {
+        TemplateSyntax::IBatis => name.split(':').next().unwrap_or(name),
+        TemplateSyntax::MyBatis => name,
+    };
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let name = name.trim_matches('_');
+    if name.is_empty() {
+        String::from("p")
+    } else {
+        String::from(name)
+    }
+}
+
+/// 规整选项
+#[derive(Debug, Clone, Default)]
+pub struct NormalizeOptions {
+    /// 保留注释，行注释转为块注释；默认只保留优化器提示
+    pub keep_comments: bool,
+    /// 输出时格式化为多行
+    pub pretty: bool,
+    /// 关键字大小写
+    pub case: CasePolicy,
+    /// 占位符样式
+    pub placeholder: PlaceholderStyle,
+    /// 以类型相符的样例值代替占位符，使语句可以直接执行
+    pub sample_values: Option<SampleValues>,
+    /// 占位符改为变量，并在语句前生成以样例值为变量赋值的前导语句
+    pub prologue: Option<SampleValues>,
+}
+
+/// 规整 `sql`：折叠空白，按策略调整关键字大小写，清理动态拼接留下的多余连接词。
+/// 标识符、字符串字面量、加引号的标识符与优化器提示保持原样。
+/// 结果为中间形式，占位符统一为 `#{name,jdbcType=...}`，留待 `normalize_bound` 替换，
+/// 已替换的占位符不再重复规整；`param_type` 为语句的参数类型，用于推断类型。
+pub fn normalize(
+    sql: &str,
+    dialect: &DialectType,
+    syntax: TemplateSyntax,
+    options: &NormalizeOptions,
+    param_type: &str,
+) -> String {
+    let mut tokens = lexer::tokenize(sql, dialect, syntax);
+    if !options.keep_comments {
+        tokens.retain(|t| t.kind != TokenKind::Comment);
+    }
+    clear_dangling(&mut tokens);
+    render(&tokens, dialect, syntax, options, param_type, None)
+}
+
+/// 输出前的最后一次规整：替换占位符，返回前导语句与规整后的 `sql`；未启用前导语句时前导语句为空
+pub fn normalize_bound(
+    sql: &str,
+    dialect: &DialectType,
+    syntax: TemplateSyntax,
+    options: &NormalizeOptions,
+    param_type: &str,
+) -> (Vec<String>, String) {
+    let mut tokens = lexer::tokenize(sql, dialect, syntax);
+    if !options.keep_comments {
+        tokens.retain(|t| t.kind != TokenKind::Comment);
+    }
+    clear_dangling(&mut tokens);
+    let mut binds = Vec::new();
+    let sql = render(
+        &tokens,
+        dialect,
+        syntax,
+        options,
+        param_type,
+        Some(&mut binds),
+    );
+    match &options.prologue {
+        Some(samples) => (prologue::compose(&binds, dialect, samples), sql),
+        None => (Vec::new(), sql),
+    }
+}
+
+/// 清理 `WHERE AND`、`, WHERE`、`, )`，以及结尾的 `AND`/`OR`/`,`
+fn clear_dangling(tokens: &mut Vec<Token>) {
+    let mut i = 0;
+    while i + 1 < tokens.len() {
+        let next = next_code(tokens, i + 1);
+        match next {
+            Some(j)
+                if tokens[i].is_word("WHERE")
+                    && (tokens[j].is_word("AND") || tokens[j].is_word("OR")) =>
+            {
+                tokens.remove(j);
+                continue;
+            }
+            Some(j) if tokens[i].is_symbol(",") && tokens[j].is_word("WHERE") => {
+                tokens[j].space_before = true;
+                tokens.remove(i);
+                continue;
+            }
+            Some(j) if tokens[i].is_symbol(",") && tokens[j].is_symbol(")") => {
+                tokens[j].space_before = false;
+                tokens.remove(i);
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    while let Some(last) = last_code(tokens) {
+        if tokens[last].is_word("AND") || tokens[last].is_word("OR") || tokens[last].is_symbol(",")
+        {
+            tokens.remove(last);
+        } else {
+            break;
+        }
+    }
+}
+
+/// 跳过注释，找到下一个有效的词
+fn next_code(tokens: &[Token], from: usize) -> Option<usize> {
+    (from..tokens.len()).find(|i| tokens[*i].kind != TokenKind::Comment)
+}
+
+fn last_code(tokens: &[Token]) -> Option<usize> {
+    (0..tokens.len())
+        .rev()
+        .find(|i| tokens[*i].kind != TokenKind::Comment)
+}
+
+/// `binds` 为 `None` 时输出中间形式，否则替换占位符，启用前导语句时收集变量
+fn render(
+    tokens: &[Token],
+    dialect: &DialectType,
+    syntax: TemplateSyntax,
+    options: &NormalizeOptions,
+    param_type: &str,
+    mut binds: Option<&mut Vec<Bind>>,
+) -> String {
+    let intermediate = binds.is_none();
+    let mut seq = 0;
+    // 字面量中的占位符与其余占位符一同编号、收集变量，`quoted` 表示占位符在字面量中
+    let mut bind = |expr: &str, quoted: bool| -> String {
+        let kind = SampleKind::of_placeholder(expr, syntax, param_type);
+        let name = param_name(expr, syntax);
+        match binds.as_deref_mut() {
+            // 尚未到最后一次规整，保留占位符与推断出的类型
+            None => match kind.jdbc_type() {
+                Some(jdbc_type) => format!("#{{{name},jdbcType={jdbc_type}}}"),
+                None => format!("#{{{name}}}"),
+            },
+            Some(binds) if options.prologue.is_some() => {
+                let reference = prologue::reference(&name, dialect);
+                if !binds.iter().any(|b| b.name == name) {
+                    binds.push(Bind { name, kind });
+                }
+                reference
+            }
+            Some(_) => match &options.sample_values {
+                Some(samples) if quoted => String::from(samples.text_value()),
+                Some(samples) => String::from(samples.value_of(expr, syntax, param_type)),
+                None => {
+                    seq += 1;
+                    options.placeholder.render(expr, syntax, dialect, seq)
+                }
+            },
+        }
+    };
+    let mut sql = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        // 紧跟在占位符后的词另起，替换后不会与占位符连成一个词
+        let glued = i > 0
+            && tokens[i - 1].kind == TokenKind::Placeholder
+            && matches!(
+                token.kind,
+                TokenKind::Word | TokenKind::Number | TokenKind::QuotedIdent
+            );
+        if (token.space_before || glued) && !sql.is_empty() {
+            sql.push(' ');
+        }
+        match token.kind {
+            // `:name`/`@name` 中的名称保持原样
+            TokenKind::Word if i > 0 && is_bind_prefix(&tokens[i - 1]) && !token.space_before => {
+                sql.push_str(&token.text)
+            }
+            TokenKind::Word => sql.push_str(&options.case.apply(&token.text)),
+            TokenKind::Placeholder => sql.push_str(&bind(&token.text, false)),
+            TokenKind::Schema => sql.push_str(SCHEMA_PLACEHOLDER),
+            // 字面量中的占位符同样留到最后一次规整
+            TokenKind::StringLit if intermediate => sql.push_str(&token.text),
+            TokenKind::StringLit => {
+                sql.push_str(&substitute_in_literal(&token.text, syntax, &mut |expr| {
+                    bind(expr, true)
+                }))
+            }
+            TokenKind::Comment => {
+                sql.push_str("/* ");
+                sql.push_str(&token.text.replace("*/", "* /"));
+                sql.push_str(" */");
+            }
+            _ => sql.push_str(&token.text),
+        }
+    }
+    sql
+}
+
+fn is_bind_prefix(token: &Token) -> bool {
+    token.is_symbol(":") || token.is_symbol("@")
+}
+
+/// 字面量中的 `${...}`/`#{...}`（`iBATIS` 另有 `$...$`/`#...#`）由框架做文本替换，同样视作占位符，
+/// 以 `bind` 按占位符内的表达式替换
+fn substitute_in_literal(
+    literal: &str,
+    syntax: TemplateSyntax,
+    bind: &mut dyn FnMut(&str) -> String,
+) -> String {
+    let mut out = String::new();
+    let mut rest = literal;
+    while let Some(start) = rest.find(['$', '#']) {
+        let delimiter = &rest[start..start + 1];
+        let after = &rest[start + 1..];
+        let braced = after.starts_with('{');
+        let end = if braced {
+            after.find('}')
+        } else if syntax == TemplateSyntax::IBatis {
+            after.find(delimiter).filter(|end| *end > 0)
+        } else {
+            None
+        };
+        match end {
+            Some(end) => {
+                out.push_str(&rest[..start]);
+                if syntax == TemplateSyntax::IBatis && delimiter == "$" && braced {
+                    out.push_str(SCHEMA_PLACEHOLDER);
+                } else if braced {
+                    out.push_str(&bind(&after[1..end]));
+                } else {
+                    out.push_str(&bind(&after[..end]));
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[..start + 1]);
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(placeholder: PlaceholderStyle) -> NormalizeOptions {
+        NormalizeOptions {
+            placeholder,
+            ..Default::default()
+        }
+    }
+
+    /// 与解析器相同的两步：先规整为中间形式，再最后一次规整
+    fn two_pass(sql: &str, dialect: &DialectType, options: &NormalizeOptions) -> String {
+        let syntax = TemplateSyntax::MyBatis;
+        let sql = normalize(sql, dialect, syntax, options, "");
+        normalize_bound(&sql, dialect, syntax, options, "").1
+    }
+
+    #[test]
+    fn numbered_placeholders_keep_following_keywords() {
+        let options = options(PlaceholderStyle::Numbered);
+        let sql = "DELETE FROM orders WHERE 1=1 AND a = #{a} AND b = #{b}";
+        assert_eq!(
+            two_pass(sql, &DialectType::Kingbase, &options),
+            "DELETE FROM orders WHERE 1=1 AND a = $1 AND b = $2"
+        );
+        // 动态元素拼接后紧挨着的关键字
+        let sql = "DELETE FROM orders WHERE 1=1 AND a = #{a}AND b = #{b}";
+        assert_eq!(
+            two_pass(sql, &DialectType::Kingbase, &options),
+            "DELETE FROM orders WHERE 1=1 AND a = $1 AND b = $2"
+        );
+    }
+
+    #[test]
+    fn numbers_do_not_swallow_keywords() {
+        let sql = two_pass(
+            "SELECT * FROM t WHERE 1=1AND a = 2.5",
+            &DialectType::MySQL,
+            &NormalizeOptions::default(),
+        );
+        assert_eq!(sql, "SELECT * FROM t WHERE 1=1AND a = 2.5");
+        let tokens = lexer::tokenize("1AND", &DialectType::MySQL, TemplateSyntax::MyBatis);
+        assert_eq!(tokens.len(), 2);
+        assert!(tokens[1].is_word("AND"));
+    }
+
+    #[test]
+    fn numbering_continues_across_included_fragments() {
+        let options = options(PlaceholderStyle::Numbered);
+        let dialect = DialectType::OpenGauss;
+        let syntax = TemplateSyntax::MyBatis;
+        let stat = normalize(
+            "x = #{x} AND __FRAGMENT__ AND z = #{z}",
+            &dialect,
+            syntax,
+            &options,
+            "",
+        );
+        let fragment = normalize("y = #{y}", &dialect, syntax, &options, "");
+        let sql = stat.replace("__FRAGMENT__", &fragment);
+        assert_eq!(
+            normalize_bound(&sql, &dialect, syntax, &options, "").1,
+            "x = $1 AND y = $2 AND z = $3"
+        );
+    }
+
+    #[test]
+    fn intermediate_form_keeps_inferred_type() {
+        let sql = normalize(
+            "a = #{user.id} AND b = '%${name}%'",
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            &NormalizeOptions::default(),
+            "java.lang.Long",
+        );
+        assert_eq!(sql, "a = #{user_id,jdbcType=NUMERIC} AND b = '%${name}%'");
+        let options = NormalizeOptions {
+            sample_values: Some(SampleValues::new(&DialectType::MySQL, &[])),
+            ..Default::default()
+        };
+        let (_, sql) = normalize_bound(
+            &sql,
+            &DialectType::MySQL,
+            TemplateSyntax::MyBatis,
+            &options,
+            "",
+        );
+        assert_eq!(sql, "a = 1 AND b = '%1%'");
+    }
+
+    #[test]
+    fn dollar_in_identifiers_is_not_a_placeholder() {
+        let options = options(PlaceholderStyle::Numbered);
+        assert_eq!(
+            two_pass(
+                "SELECT sid FROM v$session WHERE sid = #{sid}",
+                &DialectType::Oracle,
+                &options
+            ),
+            "SELECT sid FROM v$session WHERE sid = $1"
+        );
+    }
+
+    #[test]
+    fn named_styles_and_case() {
+        let sql = "select a from t where id = #{user.id,jdbcType=NUMERIC}";
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &options(PlaceholderStyle::Named)),
+            "SELECT a FROM t WHERE id = :user_id"
+        );
+        assert_eq!(
+            two_pass(
+                sql,
+                &DialectType::MySQL,
+                &options(PlaceholderStyle::Variable)
+            ),
+            "SELECT a FROM t WHERE id = @user_id"
+        );
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &NormalizeOptions::default()),
+            "SELECT a FROM t WHERE id = :?"
+        );
+    }
+
+    #[test]
+    fn placeholders_in_literals_follow_style() {
+        let sql = "a = #{a} AND b LIKE '%${b}%' AND c = #{c}";
+        assert_eq!(
+            two_pass(
+                sql,
+                &DialectType::Kingbase,
+                &options(PlaceholderStyle::Numbered)
+            ),
+            "a = $1 AND b LIKE '%$2%' AND c = $3"
+        );
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &options(PlaceholderStyle::Named)),
+            "a = :a AND b LIKE '%:b%' AND c = :c"
+        );
+    }
+
+    #[test]
+    fn placeholders_in_literals_are_prologue_binds() {
+        let options = NormalizeOptions {
+            prologue: Some(SampleValues::new(&DialectType::MySQL, &[])),
+            ..Default::default()
+        };
+        let syntax = TemplateSyntax::IBatis;
+        let sql = normalize(
+            "a = #a# AND b LIKE '%$b$%'",
+            &DialectType::MySQL,
+            syntax,
+            &options,
+            "",
+        );
+        let (prologue, sql) = normalize_bound(&sql, &DialectType::MySQL, syntax, &options, "");
+        assert_eq!(sql, "a = @a AND b LIKE '%@b%'");
+        assert_eq!(prologue, ["SET @a = '1';", "SET @b = '1';"]);
+    }
+
+    #[test]
+    fn case_policy_changes_only_keywords() {
+        let sql =
+            "Select Name, 'select x' As \"From\", count(*) from Users u where u.Status = #{status}";
+        let case = |case| NormalizeOptions {
+            case,
+            ..Default::default()
+        };
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &case(CasePolicy::UpperKeywords)),
+            "SELECT Name, 'select x' AS \"From\", count(*) FROM Users u WHERE u.Status = :?"
+        );
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &case(CasePolicy::Lower)),
+            "select Name, 'select x' as \"From\", count(*) from Users u where u.Status = :?"
+        );
+        assert_eq!(
+            two_pass(sql, &DialectType::Oracle, &case(CasePolicy::Preserve)),
+            "Select Name, 'select x' As \"From\", count(*) from Users u where u.Status = :?"
+        );
+        // `:name` 中与关键字同名的参数名保持原样
+        assert_eq!(
+            two_pass(
+                "select a from t where b = :order",
+                &DialectType::Oracle,
+                &NormalizeOptions::default()
+            ),
+            "SELECT a FROM t WHERE b = :order"
+        );
+    }
+
+    #[test]
+    fn dangling_connectors_are_cleared() {
+        let sql = two_pass(
+            "UPDATE t SET a = 1, WHERE AND id = #{id} AND",
+            &DialectType::H2,
+            &NormalizeOptions::default(),
+        );
+        assert_eq!(sql, "UPDATE t SET a = 1 WHERE id = ?");
+    }
+}
@@ -0,0 +1,42 @@
+use super::sample::{SampleKind, SampleValues};
+use crate::xbatis::def::DialectType;
+
+/// 语句用到的变量
+pub struct Bind {
+    pub name: String,
+    pub kind: SampleKind,
+}
+
+/// 变量在语句中的写法，`MySQL`、`H2`、`OceanBase` 的 `MySQL` 模式为会话变量 `@name`，
+/// 其余为绑定变量 `:name`
+pub fn reference(name: &str, dialect: &DialectType) -> String {
+    match dialect {
+        DialectType::MySQL | DialectType::H2 | DialectType::OceanBaseMySql => format!("@{name}"),
+        _ => format!(":{name}"),
+    }
+}
+
+/// 为变量赋样例值的前导语句：`MySQL` 为 `SET @name = ...;`，
+/// `Oracle` 为 `SQL*Plus` 的 `VARIABLE` 声明加 `EXEC :name := ...;`，其余方言没有前导语句
+pub fn compose(binds: &[Bind], dialect: &DialectType, samples: &SampleValues) -> Vec<String> {
+    match dialect {
+        DialectType::MySQL | DialectType::H2 | DialectType::OceanBaseMySql => binds
+            .iter()
+            .map(|b| format!("SET @{} = {};", b.name, samples.value(b.kind)))
+            .collect(),
+        DialectType::Oracle => {
+            let declares = binds.iter().map(|b| {
+                let sql_type = match b.kind {
+                    SampleKind::Number | SampleKind::Boolean => "NUMBER",
+                    _ => "VARCHAR2(4000)",
+                };
+                format!("VARIABLE {} {}", b.name, sql_type)
+            });
+            let assigns = binds
+                .iter()
+                .map(|b| format!("EXEC :{} := {};", b.name, samples.value(b.kind)));
+            declares.chain(assigns).collect()
+        }
+        _ => Vec::new(),
+    }
+}
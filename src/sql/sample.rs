@@ -0,0 +1,197 @@
+use super::lexer::TemplateSyntax;
+use crate::xbatis::def::DialectType;
+use std::collections::HashMap;
+
+/// 样例值的类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleKind {
+    Number,
+    String,
+    Date,
+    Timestamp,
+    Boolean,
+    /// 没有类型提示时使用
+    Default,
+}
+
+impl SampleKind {
+    pub fn from(name: &str) -> Option<Self> {
+        match name {
+            "number" => Some(SampleKind::Number),
+            "string" => Some(SampleKind::String),
+            "date" => Some(SampleKind::Date),
+            "timestamp" => Some(SampleKind::Timestamp),
+            "boolean" => Some(SampleKind::Boolean),
+            "default" => Some(SampleKind::Default),
+            _ => None,
+        }
+    }
+
+    /// 占位符的类别：先看占位符自身的类型提示，再看语句的参数类型，都没有时为 `Default`
+    pub fn of_placeholder(expr: &str, syntax: TemplateSyntax, param_type: &str) -> Self {
+        type_hint(expr, syntax)
+            .and_then(SampleKind::of_type)
+            .or_else(|| SampleKind::of_type(param_type))
+            .unwrap_or(SampleKind::Default)
+    }
+
+    /// 类别对应的 `jdbcType`，用于在占位符中保留推断出的类型
+    pub fn jdbc_type(&self) -> Option<&str> {
+        match self {
+            SampleKind::Number => Some("NUMERIC"),
+            SampleKind::String => Some("VARCHAR"),
+            SampleKind::Date => Some("DATE"),
+            SampleKind::Timestamp => Some("TIMESTAMP"),
+            SampleKind::Boolean => Some("BOOLEAN"),
+            SampleKind::Default => None,
+        }
+    }
+
+    /// 由 `jdbcType`、`javaType` 或 `parameterType` 推断类别，包名与大小写忽略
+    pub fn of_type(type_name: &str) -> Option<Self> {
+        let name = type_name.rsplit('.').next().unwrap_or(type_name);
+        match name.to_ascii_lowercase().as_str() {
+            "tinyint" | "smallint" | "integer" | "int" | "bigint" | "numeric" | "decimal"
+            | "number" | "float" | "double" | "real" | "short" | "long" | "byte" | "biginteger"
+            | "bigdecimal" | "_int" | "_long" | "_short" | "_double" | "_float" | "_byte" => {
+                Some(SampleKind::Number)
+            }
+            "char" | "varchar" | "nchar" | "nvarchar" | "longvarchar" | "longnvarchar" | "clob"
+            | "nclob" | "string" | "character" => Some(SampleKind::String),
+            "date" | "localdate" => Some(SampleKind::Date),
+            "timestamp" | "time" | "localdatetime" | "localtime" | "offsetdatetime"
+            | "zoneddatetime" | "instant" | "calendar" => Some(SampleKind::Timestamp),
+            "boolean" | "bit" | "_boolean" => Some(SampleKind::Boolean),
+            _ => None,
+        }
+    }
+}
+
+/// 占位符的样例值，按类别取值，可由命令行覆盖
+#[derive(Debug, Clone)]
+pub struct SampleValues {
+    values: HashMap<SampleKind, String>,
+}
+
+impl SampleValues {
+    /// 方言对应的默认值，再以 `overrides` 覆盖
+    pub fn new(dialect: &DialectType, overrides: &[(SampleKind, String)]) -> Self {
+        let (date, timestamp) = match dialect {
+            DialectType::MySQL
+            | DialectType::Sqlite
+            | DialectType::OceanBaseMySql
+            | DialectType::Db2 => ("'2000-01-01'", "'2000-01-01 00:00:00'"),
+            _ => ("DATE '2000-01-01'", "TIMESTAMP '2000-01-01 00:00:00'"),
+        };
+        let mut values = HashMap::from([
+            (SampleKind::Number, String::from("1")),
+            (SampleKind::String, String::from("'1'")),
+            (SampleKind::Date, String::from(date)),
+            (SampleKind::Timestamp, String::from(timestamp)),
+            (SampleKind::Boolean, String::from("1")),
+            (SampleKind::Default, String::from("'1'")),
+        ]);
+        for (kind, value) in overrides {
+            values.insert(*kind, value.clone());
+        }
+        SampleValues { values }
+    }
+
+    /// 占位符的样例值
+    pub fn value_of(&self, expr: &str, syntax: TemplateSyntax, param_type: &str) -> &str {
+        self.value(SampleKind::of_placeholder(expr, syntax, param_type))
+    }
+
+    pub fn value(&self, kind: SampleKind) -> &str {
+        self.values
+            .get(&kind)
+            .or_else(|| self.values.get(&SampleKind::Default))
+            .map(|v| v.as_str())
+            .unwrap_or("NULL")
+    }
+
+    /// 字面量内占位符的样例值，取字符串样例值去掉引号
+    pub fn text_value(&self) -> &str {
+        self.values
+            .get(&SampleKind::String)
+            .map(|v| v.trim_matches('\''))
+            .unwrap_or("1")
+    }
+}
+
+/// 占位符内的类型提示：`id,jdbcType=VARCHAR`、`id,javaType=int`，`iBATIS` 另有 `id:VARCHAR`
+fn type_hint(expr: &str, syntax: TemplateSyntax) -> Option<&str> {
+    let mut parts = expr.split(',').map(str::trim);
+    let name = parts.next()?;
+    let mut java_type = None;
+    for part in parts {
+        match part.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+            Some(("jdbcType", v)) => return Some(v),
+            Some(("javaType", v)) => java_type = Some(v),
+            _ => {}
+        }
+    }
+    if java_type.is_some() {
+        return java_type;
+    }
+    match syntax {
+        TemplateSyntax::IBatis => name.split(':').nth(1).map(str::trim),
+        TemplateSyntax::MyBatis => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kind_comes_from_hint_then_parameter_type() {
+        let mybatis = TemplateSyntax::MyBatis;
+        assert_eq!(
+            SampleKind::of_placeholder("id,jdbcType=BIGINT", mybatis, "java.lang.String"),
+            SampleKind::Number
+        );
+        assert_eq!(
+            SampleKind::of_placeholder("at,javaType=java.time.LocalDateTime", mybatis, ""),
+            SampleKind::Timestamp
+        );
+        assert_eq!(
+            SampleKind::of_placeholder("name", mybatis, "java.lang.String"),
+            SampleKind::String
+        );
+        assert_eq!(
+            SampleKind::of_placeholder("user", mybatis, "com.demo.User"),
+            SampleKind::Default
+        );
+        assert_eq!(
+            SampleKind::of_placeholder("day:DATE", TemplateSyntax::IBatis, ""),
+            SampleKind::Date
+        );
+        // `MyBatis` 中的 `:` 不是类型提示
+        assert_eq!(
+            SampleKind::of_placeholder("day:DATE", mybatis, ""),
+            SampleKind::Default
+        );
+    }
+
+    #[test]
+    fn values_follow_dialect_and_overrides() {
+        let samples = SampleValues::new(&DialectType::Oracle, &[]);
+        assert_eq!(samples.value(SampleKind::Date), "DATE '2000-01-01'");
+        assert_eq!(samples.value(SampleKind::Default), "'1'");
+        let samples = SampleValues::new(&DialectType::MySQL, &[]);
+        assert_eq!(samples.value(SampleKind::Date), "'2000-01-01'");
+        let samples = SampleValues::new(
+            &DialectType::MySQL,
+            &[
+                (SampleKind::Number, String::from("42")),
+                (SampleKind::String, String::from("'abc'")),
+            ],
+        );
+        assert_eq!(
+            samples.value_of("id,jdbcType=INTEGER", TemplateSyntax::MyBatis, ""),
+            "42"
+        );
+        assert_eq!(samples.text_value(), "abc");
+    }
+}
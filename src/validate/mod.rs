@@ -0,0 +1,2 @@
+/// 以 `sql` 语法校验抽取出的语句
+pub mod sql_validator;
@@ -0,0 +1,236 @@
+use crate::{
+    extract::sql_extractor::ExtractedSql,
+    sql::lexer::{self, TemplateSyntax, TokenKind},
+    xbatis::def::DialectType,
+};
+use lazy_static::lazy_static;
+use log::warn;
+use regex::Regex;
+use serde::Serialize;
+use sqlparser::{
+    ast::Statement,
+    dialect::{Dialect, GenericDialect, MySqlDialect, PostgreSqlDialect, SQLiteDialect},
+    parser::{Parser as SqlParser, ParserError},
+};
+use std::process;
+
+lazy_static! {
+    static ref POSITION_REGEX: Regex =
+        Regex::new("Line: (\\d+), Column: (\\d+)").unwrap_or_else(|e| {
+            warn!("Unable to parse the regex: {e}");
+            process::exit(-1);
+        });
+}
+
+/// 单条语句的语法错误
+#[derive(Serialize)]
+pub struct SyntaxFailure {
+    pub file: String,
+    pub key: String,
+    /// 交给语法解析的 `sql`，出错位置以此为准
+    pub sql: String,
+    pub error: String,
+    /// 出错位置，解析器未给出时为 `0`
+    pub line: u64,
+    pub column: u64,
+}
+
+/// 校验报告
+#[derive(Serialize, Default)]
+pub struct ValidateReport {
+    pub total: usize,
+    pub failures: Vec<SyntaxFailure>,
+}
+
+/// 方言对应的语法，没有专门语法的方言使用通用语法
+fn grammar(dialect_type: &DialectType) -> Box<dyn Dialect> {
+    match dialect_type {
+        DialectType::MySQL | DialectType::OceanBaseMySql => Box::new(MySqlDialect {}),
+        DialectType::Sqlite => Box::new(SQLiteDialect {}),
+        DialectType::Kingbase | DialectType::OpenGauss => Box::new(PostgreSqlDialect {}),
+        _ => Box::new(GenericDialect {}),
+    }
+}
+
+/// 第 `seq` 个占位符在语法中的写法，`PostgreSQL` 系只认 `$n`
+fn grammar_placeholder(dialect_type: &DialectType, seq: usize) -> String {
+    match dialect_type {
+        DialectType::Kingbase | DialectType::OpenGauss => format!("${seq}"),
+        _ => String::from("?"),
+    }
+}
+
+/// `Oracle` 系，通用语法不认其外连接标记等写法
+fn is_oracle(dialect_type: &DialectType) -> bool {
+    matches!(
+        dialect_type,
+        DialectType::Oracle | DialectType::Dm | DialectType::OceanBaseOracle
+    )
+}
+
+/// 把中间形式改写为语法可以解析的 `sql`：占位符换成语法认可的写法，字面量保持原样，注释去掉；
+/// `Oracle` 系另外去掉 `(+)` 与 `RETURNING ... INTO` 的 `INTO` 部分，`q'[...]'` 改为普通字符串
+pub fn grammar_sql(template: &str, dialect_type: &DialectType) -> String {
+    let oracle = is_oracle(dialect_type);
+    let tokens = lexer::tokenize(template, dialect_type, TemplateSyntax::MyBatis);
+    let mut sql = String::new();
+    let mut seq = 0;
+    let mut returning = false;
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        if oracle && is_outer_join_mark(&tokens[i..]) {
+            i += 3;
+            continue;
+        }
+        if oracle && returning && token.is_word("INTO") {
+            break;
+        }
+        returning |= token.is_word("RETURNING");
+        i += 1;
+        if token.kind == TokenKind::Comment {
+            continue;
+        }
+        if token.space_before && !sql.is_empty() {
+            sql.push(' ');
+        }
+        match token.kind {
+            TokenKind::Placeholder => {
+                seq += 1;
+                sql.push_str(&grammar_placeholder(dialect_type, seq));
+            }
+            TokenKind::StringLit if token.text.starts_with(['q', 'Q']) => {
+                // `q'[...]'`，去掉定界符后按普通字符串转义
+                let body = &token.text[3..token.text.len() - 2];
+                sql.push_str(&format!("'{}'", body.replace('\'', "''")));
+            }
+            _ => sql.push_str(&token.text),
+        }
+    }
+    sql
+}
+
+/// `Oracle` 的外连接标记 `(+)`
+fn is_outer_join_mark(tokens: &[lexer::Token]) -> bool {
+    tokens.len() >= 3
+        && tokens[0].is_symbol("(")
+        && tokens[1].is_symbol("+")
+        && tokens[2].is_symbol(")")
+}
+
+/// 以方言语法解析中间形式的语句
+pub fn parse(template: &str, dialect_type: &DialectType) -> Result<Vec<Statement>, ParserError> {
+    SqlParser::parse_sql(
+        grammar(dialect_type).as_ref(),
+        &grammar_sql(template, dialect_type),
+    )
+}
+
+/// 逐条解析规整后的语句，收集语法错误
+pub fn validate(sqls: &[ExtractedSql], dialect_type: &DialectType) -> ValidateReport {
+    let mut report = ValidateReport {
+        total: sqls.len(),
+        failures: Vec::new(),
+    };
+    for stat in sqls {
+        let sql = grammar_sql(&stat.template, dialect_type);
+        if let Err(e) = SqlParser::parse_sql(grammar(dialect_type).as_ref(), &sql) {
+            let error = e.to_string();
+            let (line, column) = position(&error);
+            report.failures.push(SyntaxFailure {
+                file: stat.file.clone(),
+                key: stat.key(),
+                sql,
+                error,
+                line,
+                column,
+            });
+        }
+    }
+    report
+}
+
+fn position(error: &str) -> (u64, u64) {
+    POSITION_REGEX
+        .captures(error)
+        .map(|c| {
+            (
+                c[1].parse::<u64>().unwrap_or(0),
+                c[2].parse::<u64>().unwrap_or(0),
+            )
+        })
+        .unwrap_or((0, 0))
+}
+
+/// 文本格式输出
+pub fn print_text(report: &ValidateReport) {
+    for failure in report.failures.iter() {
+        println!(
+            "{}: {} [{}:{}] {}",
+            failure.file, failure.key, failure.line, failure.column, failure.error
+        );
+        println!("    {}", failure.sql);
+    }
+    println!(
+        "validated: {}, failed: {}",
+        report.total,
+        report.failures.len()
+    );
+}
+
+/// `json` 格式输出
+pub fn print_json(report: &ValidateReport) {
+    match serde_json::to_string_pretty(report) {
+        Ok(json) => println!("{json}"),
+        Err(e) => warn!("serialize validate report failed: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn placeholders_are_replaced_outside_literals() {
+        let sql = grammar_sql(
+            "SELECT * FROM t WHERE mail = 'x@1y.com' AND id = #{id,jdbcType=NUMERIC}",
+            &DialectType::MySQL,
+        );
+        assert_eq!(sql, "SELECT * FROM t WHERE mail = 'x@1y.com' AND id = ?");
+    }
+
+    #[test]
+    fn postgres_family_gets_numbered_placeholders() {
+        let template = "UPDATE t SET a = #{a} WHERE b = #{b}";
+        assert_eq!(
+            grammar_sql(template, &DialectType::Kingbase),
+            "UPDATE t SET a = $1 WHERE b = $2"
+        );
+        assert!(parse(template, &DialectType::OpenGauss).is_ok());
+    }
+
+    #[test]
+    fn oracle_only_constructs_are_tolerated() {
+        for template in [
+            "SELECT a.id FROM a, b WHERE a.id = b.id(+) AND b.x = #{x}",
+            "INSERT INTO t (a) VALUES (#{a}) RETURNING a INTO #{id}",
+            "SELECT * FROM t WHERE x = q'[it's]'",
+        ] {
+            assert!(parse(template, &DialectType::Oracle).is_ok(), "{template}");
+        }
+        assert!(parse(
+            "SELECT a.id FROM a, b WHERE a.id = b.id(+)",
+            &DialectType::Dm
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn comments_are_dropped() {
+        let sql = grammar_sql(
+            "SELECT /* ids */ id /*+ FULL(t) */ FROM t",
+            &DialectType::Oracle,
+        );
+        assert_eq!(sql, "SELECT id /*+ FULL(t) */ FROM t");
+    }
+}
@@ -0,0 +1,2 @@
+/// 监听模式
+pub mod xml_watcher;
@@ -0,0 +1,320 @@
+use crate::{
+    args::args_parser::Args,
+    diff::sql_diff::{self, DiffReport},
+    extract::sql_extractor::{self, ExtractedSql},
+    save::sql_saver,
+    xbatis::{
+        def::{DynamicNode, ParsedXml},
+        xbatis_parser::Parser,
+    },
+};
+use log::{info, warn};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{self, Path, PathBuf},
+    process,
+    sync::mpsc,
+    time::Duration,
+};
+
+/// 合并短时间内的连续事件，避免保存一次文件触发多轮解析
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// 监听期间常驻内存的解析结果
+struct Workspace {
+    /// 绝对路径 -> 文件名，文件名与扫描结果保持一致
+    known: HashMap<PathBuf, String>,
+    parsed: BTreeMap<String, ParsedXml>,
+    extracted: BTreeMap<String, Vec<ExtractedSql>>,
+    global_inc_map: HashMap<String, String>,
+    global_trees: HashMap<String, DynamicNode>,
+}
+
+impl Workspace {
+    fn new(files: &[String]) -> Self {
+        let mut known = HashMap::new();
+        for file in files {
+            known.insert(absolute(Path::new(file)), file.clone());
+        }
+        Workspace {
+            known,
+            parsed: BTreeMap::new(),
+            extracted: BTreeMap::new(),
+            global_inc_map: HashMap::new(),
+            global_trees: HashMap::new(),
+        }
+    }
+
+    /// 首次全量解析
+    fn load(&mut self, parser: &dyn Parser) {
+        let files: Vec<String> = self.known.values().cloned().collect();
+        for file in files.iter() {
+            if let Some(parsed) = sql_extractor::parse_file(parser, file) {
+                self.parsed.insert(file.clone(), parsed);
+            }
+        }
+        self.rebuild_inc_map();
+        for (file, parsed) in self.parsed.iter() {
+            let sqls = self.resolve(parser, file, parsed);
+            self.extracted.insert(file.clone(), sqls);
+        }
+    }
+
+    /// 事件中的路径转成文件名，新建的文件沿用事件中的路径
+    fn file_of(&mut self, path: &Path) -> String {
+        let abs = absolute(path);
+        self.known
+            .entry(abs)
+            .or_insert_with(|| path.to_string_lossy().to_string())
+            .clone()
+    }
+
+    /// 只重新解析变化的文件，再重新替换变化文件与依赖它们的文件中的语句
+    fn update(&mut self, parser: &dyn Parser, changed: &BTreeSet<String>) -> DiffReport {
+        let mut inc_keys: BTreeSet<String> = BTreeSet::new();
+        for file in changed.iter() {
+            if let Some(old) = self.parsed.remove(file) {
+                inc_keys.extend(inc_keys_of(&old));
+            }
+            if Path::new(file).is_file() {
+                if let Some(parsed) = sql_extractor::parse_file(parser, file) {
+                    inc_keys.extend(inc_keys_of(&parsed));
+                    self.parsed.insert(file.clone(), parsed);
+                }
+            } else {
+                info!("[{file}] removed");
+            }
+        }
+        self.rebuild_inc_map();
+        let mut affected = changed.clone();
+        affected.extend(self.dependents(&inc_keys));
+        let mut old_sqls = Vec::new();
+        let mut new_sqls = Vec::new();
+        for file in affected.iter() {
+            if let Some(mut sqls) = self.extracted.remove(file) {
+                old_sqls.append(&mut sqls);
+            }
+            if let Some(parsed) = self.parsed.get(file) {
+                let sqls = self.resolve(parser, file, parsed);
+                new_sqls.extend(sqls.iter().cloned());
+                self.extracted.insert(file.clone(), sqls);
+            }
+        }
+        info!(
+            "re-parsed {} files, re-resolved {} files",
+            changed.len(),
+            affected.len()
+        );
+        sql_diff::diff(&old_sqls, &new_sqls)
+    }
+
+    /// 经由 `include` 直接或间接引用了给定片段的文件
+    fn dependents(&self, inc_keys: &BTreeSet<String>) -> BTreeSet<String> {
+        let mut keys = inc_keys.clone();
+        let mut files = BTreeSet::new();
+        loop {
+            let mut found = false;
+            for (file, parsed) in self.parsed.iter() {
+                if !refers_any(parsed, &keys) {
+                    continue;
+                }
+                found |= files.insert(file.clone());
+                for key in inc_keys_of(parsed) {
+                    found |= keys.insert(key);
+                }
+            }
+            if !found {
+                break;
+            }
+        }
+        files
+    }
+
+    fn rebuild_inc_map(&mut self) {
+        self.global_inc_map.clear();
+        self.global_trees.clear();
+        for parsed in self.parsed.values() {
+            sql_extractor::register_sql_parts(
+                &mut self.global_inc_map,
+                &mut self.global_trees,
+                parsed,
+            );
+        }
+    }
+
+    fn resolve(&self, parser: &dyn Parser, file: &str, parsed: &ParsedXml) -> Vec<ExtractedSql> {
+        sql_extractor::resolve_file(
+            parser,
+            file,
+            parsed,
+            &self.global_inc_map,
+            &self.global_trees,
+        )
+    }
+
+    /// 重写 `result.sql`
+    fn write(&mut self, parser: &dyn Parser, output_dir: &String) {
+        let mut sql_stores = Vec::new();
+        let mut query_no = 0;
+        for (file, parsed) in self.parsed.iter() {
+            let mut sql_store = Vec::new();
+            parser.fill_parsed(file, parsed, &mut sql_store, &mut self.global_inc_map);
+            let sql_store = sql_store
+                .iter()
+                .map(|line| parser.finalize_sql(line, &self.global_inc_map, &mut query_no))
+                .collect();
+            sql_stores.push(sql_store);
+        }
+        sql_stores.push(parser.explain_epilogue());
+        sql_saver::write_result(output_dir, &sql_stores);
+        info!("write to {output_dir}/result.sql");
+    }
+}
+
+/// 文件中的 `<sql>` 片段在全局的键
+fn inc_keys_of(parsed: &ParsedXml) -> Vec<String> {
+    parsed
+        .sql_parts
+        .keys()
+        .map(|id| format!("{}.{}", parsed.namespace, id))
+        .collect()
+}
+
+/// 文件中是否有语句或片段引用了给定的全局片段
+fn refers_any(parsed: &ParsedXml, keys: &BTreeSet<String>) -> bool {
+    let targets: Vec<String> = keys
+        .iter()
+        .map(|key| format!("__INCLUDE_ID_{key}_END__"))
+        .collect();
+    let refers = |sql: &String| targets.iter().any(|t| sql.contains(t.as_str()));
+    parsed
+        .statements
+        .iter()
+        .any(|s| refers(&s.sql) || refers(&s.sql_key.sql))
+        || parsed.sql_parts.values().any(refers)
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_xml(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "xml")
+}
+
+/// 全量解析一次后常驻，源码目录中 `xml` 变化时增量更新 `result.sql`
+pub fn watch(args: &Args, parser: &dyn Parser, files: &[String]) {
+    let mut workspace = Workspace::new(files);
+    workspace.load(parser);
+    workspace.write(parser, &args.output_dir);
+    let (tx, rx) = mpsc::channel::<notify::Result<Event>>();
+    let mut watcher = notify::recommended_watcher(tx).unwrap_or_else(|e| {
+        warn!("create file watcher failed: {e}");
+        process::exit(-1);
+    });
+    for dir in args.sources.dirs.iter() {
+        if let Err(e) = watcher.watch(Path::new(dir), RecursiveMode::Recursive) {
+            warn!("watch [{dir}] failed: {e}");
+            process::exit(-1);
+        }
+    }
+    if args.sources.files_from.is_some() {
+        for file in files.iter() {
+            if let Err(e) = watcher.watch(Path::new(file), RecursiveMode::NonRecursive) {
+                warn!("watch [{file}] failed: {e}");
+            }
+        }
+    }
+    info!("watching for changes, press Ctrl-C to stop");
+    while let Ok(first) = rx.recv() {
+        let mut changed = BTreeSet::new();
+        collect_changed(&mut workspace, &mut changed, first);
+        while let Ok(event) = rx.recv_timeout(DEBOUNCE) {
+            collect_changed(&mut workspace, &mut changed, event);
+        }
+        if changed.is_empty() {
+            continue;
+        }
+        let report = workspace.update(parser, &changed);
+        workspace.write(parser, &args.output_dir);
+        sql_diff::print_summary(&report);
+    }
+}
+
+fn collect_changed(
+    workspace: &mut Workspace,
+    changed: &mut BTreeSet<String>,
+    event: notify::Result<Event>,
+) {
+    let event = match event {
+        Ok(event) => event,
+        Err(e) => {
+            warn!("watch error: {e}");
+            return;
+        }
+    };
+    if !matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) {
+        return;
+    }
+    for path in event.paths.iter().filter(|p| is_xml(p)) {
+        changed.insert(workspace.file_of(path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xbatis::{def::DialectType, mybatis_parser};
+    use std::fs;
+
+    fn mapper(namespace: &str, body: &str) -> String {
+        format!(
+            "<!DOCTYPE mapper PUBLIC \"-//mybatis.org//DTD Mapper 3.0//EN\" \"\">\n<mapper namespace=\"{namespace}\">{body}</mapper>"
+        )
+    }
+
+    #[test]
+    fn fragment_changes_re_resolve_dependent_files() {
+        let dir = std::env::temp_dir().join(format!("xbatis2sql-watch-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str| dir.join(name).to_string_lossy().to_string();
+        let (a, b, c) = (file("a.xml"), file("b.xml"), file("c.xml"));
+        fs::write(&a, mapper("a", r#"<sql id="cond">y = 1</sql>"#)).unwrap();
+        fs::write(
+            &b,
+            mapper(
+                "b",
+                r#"<select id="s">SELECT * FROM t WHERE <include refid="a.cond"/></select>"#,
+            ),
+        )
+        .unwrap();
+        fs::write(&c, mapper("c", r#"<select id="s">SELECT 1</select>"#)).unwrap();
+        let parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        let mut workspace = Workspace::new(&[a.clone(), b.clone(), c.clone()]);
+        workspace.load(&parser);
+        assert_eq!(
+            workspace.extracted[&b][0].sql,
+            "SELECT * FROM t WHERE y = 1"
+        );
+
+        fs::write(&a, mapper("a", r#"<sql id="cond">y = 2</sql>"#)).unwrap();
+        let report = workspace.update(&parser, &BTreeSet::from([a.clone()]));
+        assert_eq!(report.changed.len(), 1);
+        assert_eq!(report.changed[0].key, "b.s");
+        assert_eq!(
+            workspace.extracted[&b][0].sql,
+            "SELECT * FROM t WHERE y = 2"
+        );
+
+        fs::remove_file(&c).unwrap();
+        let report = workspace.update(&parser, &BTreeSet::from([c.clone()]));
+        assert_eq!(report.removed.len(), 1);
+        assert!(!workspace.parsed.contains_key(&c));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
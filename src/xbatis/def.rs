@@ -1,12 +1,53 @@
-use log::warn;
-use regex::Regex;
-use std::process;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 pub enum DialectType {
     Oracle,
     MySQL,
+    /// 嵌入式数据库，多用于单元测试
+    Sqlite,
+    H2,
+    /// 达梦，兼容 `Oracle`
+    Dm,
+    /// 人大金仓，基于 `PostgreSQL`
+    Kingbase,
+    /// `openGauss`/`GaussDB`，基于 `PostgreSQL`
+    OpenGauss,
+    /// `OceanBase` 的 `Oracle` 模式
+    OceanBaseOracle,
+    /// `OceanBase` 的 `MySQL` 模式
+    OceanBaseMySql,
+    /// 标识行查询 `SYSIBM.SYSDUMMY1`
+    Db2,
 }
 
+/// 执行计划的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExplainFormat {
+    /// `explain plan for`，`Oracle` 每条语句后查询 `DBMS_XPLAN.DISPLAY`；`MySQL` 为 `explain`
+    #[default]
+    Text,
+    /// `MySQL`、`OceanBase` 的 `EXPLAIN FORMAT=JSON`，`Kingbase`、`openGauss` 的 `EXPLAIN (FORMAT JSON)`
+    Json,
+    /// `MySQL 8`、`H2`、`Kingbase`、`openGauss` 的 `EXPLAIN ANALYZE`
+    Analyze,
+    /// `Oracle` 以语句标识的摘要为 `STATEMENT_ID` 写入 `PLAN_TABLE`，末尾统一查询
+    PlanTable,
+}
+
+impl ExplainFormat {
+    pub fn from(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(ExplainFormat::Text),
+            "json" => Some(ExplainFormat::Json),
+            "analyze" => Some(ExplainFormat::Analyze),
+            "plan-table" => Some(ExplainFormat::PlanTable),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub enum Mode {
     Statement,
     Select,
@@ -30,8 +71,21 @@ impl Mode {
             _ => panic!("unkown mode"),
         }
     }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Mode::Statement => "statement",
+            Mode::Select => "select",
+            Mode::Insert => "insert",
+            Mode::Update => "update",
+            Mode::Delete => "delete",
+            Mode::SelectKey => "selectKey",
+            Mode::SqlPart => "sql",
+        }
+    }
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SqlKey {
     /// 键名
     pub key: String,
@@ -39,35 +93,84 @@ pub struct SqlKey {
     pub sql: String,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct SqlStatement {
-    pub _mode: Mode,
+    pub mode: Mode,
     pub id: String,
     pub sql: String,
+    /// `parameterType`/`parameterClass`
+    pub param_type: String,
+    /// 在文件中的行号，从 `1` 开始
+    pub line: u64,
     pub has_include: bool,
     pub has_sql_key: bool,
     pub sql_key: SqlKey,
+    /// 动态元素结构，不含取键语句
+    pub dynamic: DynamicNode,
 }
 
-impl SqlStatement {
-    pub fn new(
-        mode: Mode,
-        id: String,
-        sql: String,
-        has_include: bool,
-        has_sql_key: bool,
-        sql_key: SqlKey,
-    ) -> Self {
-        SqlStatement {
-            _mode: mode,
-            id,
-            sql,
-            has_include,
-            has_sql_key,
-            sql_key,
-        }
+/// 语句中的元素，保留条件与嵌套关系，根为语句本身
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct DynamicNode {
+    /// 元素名，小写
+    pub tag: String,
+    /// 属性，`test`、`collection`、`prepend` 等
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<DynamicPart>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum DynamicPart {
+    Text(String),
+    Element(DynamicNode),
+}
+
+impl DynamicNode {
+    /// 属性值，属性名忽略大小写
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// 是否按条件输出：`<if>`、`<when>`、`<otherwise>`、循环，以及 `iBATIS` 的 `<isXxx>`
+    pub fn is_conditional(&self) -> bool {
+        matches!(
+            self.tag.as_str(),
+            "if" | "when" | "otherwise" | "foreach" | "iterate"
+        ) || self.tag.starts_with("is")
     }
 }
 
+/// 解析结果的版本，`ParsedXml` 的结构或 `xml` 的解析逻辑每次变化都须递增，旧缓存随之失效
+pub const PARSED_VERSION: u32 = 8;
+
+/// `<sql>` 片段的定义
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SqlPart {
+    pub id: String,
+    /// 片段的 `sql`，尚未替换 `include`
+    pub sql: String,
+    /// 片段所在行
+    pub line: u64,
+    /// 动态元素结构，根为 `<sql>` 本身
+    pub dynamic: DynamicNode,
+}
+
+/// 单个文件的解析结果，尚未替换 `include`，可缓存
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ParsedXml {
+    /// 命名空间
+    pub namespace: String,
+    /// 语句集
+    pub statements: Vec<SqlStatement>,
+    /// 本文件内的 `<sql>` 片段
+    pub sql_parts: HashMap<String, String>,
+    /// 本文件内 `<sql>` 片段的定义，按出现顺序
+    pub sql_part_defs: Vec<SqlPart>,
+}
+
 /// 解析过程中数据
 pub struct XmlParsedState {
     /// 过程中变化
@@ -87,10 +190,20 @@ pub struct XmlParsedState {
     pub has_sql_key: bool,
     /// 当前ID
     pub current_id: String,
+    /// 当前语句的参数类型
+    pub current_param_type: String,
+    /// 当前语句所在行
+    pub current_line: u64,
+    /// 最近读到的元素所在行
+    pub element_line: u64,
     /// 取键语句ID
     pub current_key_id: String,
     /// 循环定义
     pub loop_def: LoopDef,
+    /// 当前语句中尚未闭合的元素，栈底为语句本身
+    pub dynamic_stack: Vec<DynamicNode>,
+    /// 刚经过元素的开始或结束，下一段内容可能与前文粘连
+    pub at_boundary: bool,
 
     /// 过程中累计
 
@@ -100,6 +213,10 @@ pub struct XmlParsedState {
     pub key_sql_builder: String,
     /// 语句集
     pub statements: Vec<SqlStatement>,
+    /// `<sql>` 片段
+    pub sql_parts: HashMap<String, String>,
+    /// `<sql>` 片段的定义
+    pub sql_part_defs: Vec<SqlPart>,
 
     /// 过程中不再变化
 
@@ -120,12 +237,19 @@ impl XmlParsedState {
             sql_builder: String::from(""),
             key_sql_builder: String::from(""),
             current_id: String::from(""),
+            current_param_type: String::from(""),
+            current_line: 0,
+            element_line: 0,
             current_key_id: String::from(""),
             loop_def: LoopDef {
                 suffix: String::from(""),
                 separator: String::from(""),
             },
+            dynamic_stack: Vec::new(),
+            at_boundary: false,
             statements: Vec::new(),
+            sql_parts: HashMap::new(),
+            sql_part_defs: Vec::new(),
             filename: String::from(""),
         }
     }
@@ -137,33 +261,20 @@ impl XmlParsedState {
         self.has_include = false;
         self.has_sql_key = false;
         self.current_id = String::from("");
+        self.current_param_type = String::from("");
+        self.current_line = 0;
         self.current_key_id = String::from("");
         self.loop_def = LoopDef {
             suffix: String::from(""),
             separator: String::from(""),
         };
+        self.dynamic_stack.clear();
+        self.at_boundary = false;
         self.sql_builder.clear();
         self.key_sql_builder.clear();
     }
 }
 
-pub struct RegexReplacement {
-    pub regex: Regex,
-    pub target: String,
-}
-
-impl RegexReplacement {
-    pub fn new(regex: &str, target: &str) -> Self {
-        RegexReplacement {
-            regex: Regex::new(regex).unwrap_or_else(|e| {
-                warn!("failed to compile regex: {e}");
-                process::exit(-1);
-            }),
-            target: String::from(target),
-        }
-    }
-}
-
 pub struct LoopDef {
     pub suffix: String,
     pub separator: String,
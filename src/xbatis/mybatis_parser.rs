@@ -1,8 +1,9 @@
 use super::{
-    def::{DialectType, RegexReplacement, XmlParsedState},
+    def::{DialectType, ExplainFormat, XmlParsedState},
     parse_helper,
-    xbatis_parser::{var_placeholder, Parser},
+    xbatis_parser::Parser,
 };
+use crate::sql::{lexer::TemplateSyntax, normalizer::NormalizeOptions};
 use lazy_static::lazy_static;
 use log::warn;
 use regex::Regex;
@@ -18,41 +19,21 @@ lazy_static! {
 
 /// `MyBatis` 实现
 pub fn create_mybatis_parser(dialect_type: DialectType) -> MyBatisParser {
-    let re_vec;
-    {
-        re_vec = create_replcements(&dialect_type);
-    }
     MyBatisParser {
         dialect_type,
-        re_vec,
+        normalize_options: NormalizeOptions::default(),
         gen_explain: false,
+        explain_format: ExplainFormat::Text,
         replace_num: 0,
         sql_limit: 0,
     }
 }
 
-fn create_replcements(dialect_type: &DialectType) -> Vec<RegexReplacement> {
-    let placeholder = var_placeholder(dialect_type);
-    vec![
-        RegexReplacement::new("[\t ]?--[^\n]*\n", ""),
-        RegexReplacement::new("[\r\n\t ]+", " "),
-        RegexReplacement::new("\\$\\{[^${]+\\}\\.", "__REPLACE_SCHEMA__."),
-        RegexReplacement::new("#\\{[^#{]+\\}", placeholder),
-        RegexReplacement::new("\\$\\{[^${]+\\}", placeholder),
-        RegexReplacement::new("WHERE[ ]+AND[ ]+", "WHERE "),
-        RegexReplacement::new("WHERE[ ]+OR[ ]+", "WHERE "),
-        RegexReplacement::new(",[ ]+WHERE", " WHERE"),
-        RegexReplacement::new("[ ]*,[ ]*\\)", ")"),
-        RegexReplacement::new("AND[ ]*$", ""),
-        RegexReplacement::new("OR[ ]*$", ""),
-        RegexReplacement::new(",$", ""),
-    ]
-}
-
 pub struct MyBatisParser {
     dialect_type: DialectType,
-    re_vec: Vec<RegexReplacement>,
+    normalize_options: NormalizeOptions,
     gen_explain: bool,
+    explain_format: ExplainFormat,
     replace_num: i16,
     sql_limit: i16,
 }
@@ -66,6 +47,14 @@ impl Parser for MyBatisParser {
         self.gen_explain
     }
 
+    fn setup_explain_format(&mut self, explain_format: ExplainFormat) {
+        self.explain_format = explain_format;
+    }
+
+    fn explain_format(&self) -> ExplainFormat {
+        self.explain_format
+    }
+
     fn setup_replace_num(&mut self, replace_num: i16) {
         self.replace_num = replace_num;
     }
@@ -78,11 +67,11 @@ impl Parser for MyBatisParser {
         self.replace_num
     }
 
-    fn is_sql_limit(&self) -> bool{
+    fn is_sql_limit(&self) -> bool {
         self.sql_limit > 0
     }
 
-    fn sql_limit(&self) -> i16{
+    fn sql_limit(&self) -> i16 {
         self.sql_limit
     }
 
@@ -106,10 +95,10 @@ impl Parser for MyBatisParser {
         } else if element_name == "trim" {
             state.in_loop = true;
             parse_helper::search_matched_attr(attributes, "prefix", |attr| {
-                self.fill_content(state, attr.value.clone());
+                self.fill_content(state, format!(" {} ", attr.value));
             });
             parse_helper::search_matched_attr(attributes, "suffix", |attr| {
-                state.loop_def.suffix = attr.value.clone();
+                state.loop_def.suffix = format!(" {} ", attr.value);
             });
         } else if element_name == "foreach" {
             state.in_loop = true;
@@ -142,7 +131,15 @@ impl Parser for MyBatisParser {
         }
     }
 
-    fn vec_regex(&self) -> &Vec<RegexReplacement> {
-        &self.re_vec
+    fn template_syntax(&self) -> TemplateSyntax {
+        TemplateSyntax::MyBatis
+    }
+
+    fn setup_normalize_options(&mut self, normalize_options: NormalizeOptions) {
+        self.normalize_options = normalize_options;
+    }
+
+    fn normalize_options(&self) -> &NormalizeOptions {
+        &self.normalize_options
     }
 }
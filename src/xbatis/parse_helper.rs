@@ -10,7 +10,7 @@ use xml::attribute::OwnedAttribute;
 
 static REGEX_INC_MAP_INIT: AtomicBool = AtomicBool::new(false);
 
-/// 替换 `include`，用对应的 `sql` 进行合并
+/// 替换 `include`，用对应的 `sql` 进行合并；`id` 不区分大小写
 pub fn replace_included_sql(orig_sql: &str, id: &str, sql_part: &str) -> String {
     let rx = gen_regex_by_id(id);
     let replaced = sql_part;
@@ -27,7 +27,7 @@ fn gen_regex_by_id(id: &str) -> Regex {
     regex_inc_map
         .entry(replace_target.clone())
         .or_insert_with(|| {
-            Regex::new(replace_target.as_str()).unwrap_or_else(|e| {
+            Regex::new(format!("(?i){replace_target}").as_str()).unwrap_or_else(|e| {
                 warn!("build regex[{replace_target}] failed: {e}");
                 process::exit(-1);
             })
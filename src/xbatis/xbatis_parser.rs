@@ -1,18 +1,35 @@
 use super::{
-    def::{DialectType, Mode, RegexReplacement, SqlKey, SqlStatement, XmlParsedState},
+    def::{
+        DialectType, DynamicNode, DynamicPart, ExplainFormat, Mode, ParsedXml, SqlKey, SqlPart,
+        SqlStatement, XmlParsedState,
+    },
     parse_helper::{match_statement, replace_included_sql, search_matched_attr},
 };
+use crate::sql::{
+    formatter,
+    lexer::TemplateSyntax,
+    normalizer::{self, NormalizeOptions},
+};
 use lazy_static::lazy_static;
 use log::{debug, info, warn};
 use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     fs,
-    io::BufReader,
+    io::{BufReader, Read},
     process,
     sync::{Arc, Mutex},
 };
-use xml::{attribute::OwnedAttribute, name::OwnedName, reader::XmlEvent, EventReader};
+use xml::{
+    attribute::OwnedAttribute, common::Position, name::OwnedName, reader::XmlEvent, EventReader,
+};
+
+/// `plan-table` 格式下，末尾统一查询各语句的执行计划
+const PLAN_TABLE_QUERY: &str = "SELECT STATEMENT_ID, ID, PARENT_ID, DEPTH, OPERATION, OPTIONS, OBJECT_OWNER, OBJECT_NAME, COST, CARDINALITY, BYTES, ACCESS_PREDICATES, FILTER_PREDICATES FROM PLAN_TABLE ORDER BY STATEMENT_ID, ID;";
+
+/// `DB2` 的 `QUERYNO` 替代符，输出时按语句在 `result.sql` 中的顺序编号
+const QUERY_NO_PLACEHOLDER: &str = "__QUERY_NO__";
 
 lazy_static! {
     static ref XML_REGEX: Regex = Regex::new("XML-FILE:").unwrap_or_else(|e| {
@@ -39,6 +56,10 @@ pub trait Parser {
 
     fn is_gen_explain(&self) -> bool;
 
+    fn setup_explain_format(&mut self, explain_format: ExplainFormat);
+
+    fn explain_format(&self) -> ExplainFormat;
+
     fn setup_replace_num(&mut self, replace_num: i16);
 
     fn setup_sql_limit(&mut self, sql_limit: i16);
@@ -51,6 +72,12 @@ pub trait Parser {
 
     fn dialect_type(&self) -> &DialectType;
 
+    fn template_syntax(&self) -> TemplateSyntax;
+
+    fn setup_normalize_options(&mut self, normalize_options: NormalizeOptions);
+
+    fn normalize_options(&self) -> &NormalizeOptions;
+
     fn parse(
         &self,
         file: &String,
@@ -72,30 +99,69 @@ pub trait Parser {
         &self,
         arc_global_inc_map: Arc<Mutex<HashMap<String, String>>>,
         sql: &str,
+        query_no: &mut u32,
     ) -> String {
         if let Ok(global_inc_map) = arc_global_inc_map.lock() {
-            let sql = self.replace_inc_between_xml(&String::from(sql), &global_inc_map);
-            self.replace_sql_by_regex(&sql)
+            self.finalize_sql(sql, &global_inc_map, query_no)
         } else {
             "".to_string()
         }
     }
 
-    fn replace_sql_by_regex(&self, origin_sql: &str) -> String {
-        let regex_replacements = self.vec_regex();
-        let mut sql;
-        if XML_REGEX.is_match(origin_sql)
-            || STAT_REGEX.is_match(origin_sql)
-            || ORA_QUERY_PLAN_REGEX.is_match(origin_sql)
-        {
-            sql = String::from(origin_sql);
+    /// 输出前的最后一步：替换跨文件的 `include`，规整，按需格式化为多行，前导语句置于语句之前；
+    /// 须按输出顺序调用，`query_no` 为本次输出已编到的 `QUERYNO`
+    fn finalize_sql(
+        &self,
+        sql: &str,
+        global_inc_map: &HashMap<String, String>,
+        query_no: &mut u32,
+    ) -> String {
+        let sql = self.replace_inc_between_xml(&String::from(sql), global_inc_map);
+        let (mut lines, mut sql) = self.bind_stat_sql(&sql, "");
+        if sql.contains(QUERY_NO_PLACEHOLDER) {
+            *query_no += 1;
+            sql = sql.replace(QUERY_NO_PLACEHOLDER, &query_no.to_string());
+        }
+        if self.normalize_options().pretty && !is_marker(&sql) {
+            lines.push(formatter::format(
+                &sql,
+                self.dialect_type(),
+                self.template_syntax(),
+            ));
         } else {
-            sql = String::from(origin_sql.to_ascii_uppercase().trim());
-            for regex_replacement in regex_replacements.iter() {
-                sql = self.regex_clear_and_push(&sql, regex_replacement);
-            }
+            lines.push(sql);
+        }
+        lines.join("\n")
+    }
+
+    /// 最后一次规整单条语句，占位符改为变量时一并返回前导语句
+    fn bind_stat_sql(&self, origin_sql: &str, param_type: &str) -> (Vec<String>, String) {
+        if is_marker(origin_sql) {
+            (Vec::new(), String::from(origin_sql))
+        } else {
+            normalizer::normalize_bound(
+                origin_sql,
+                self.dialect_type(),
+                self.template_syntax(),
+                self.normalize_options(),
+                param_type,
+            )
+        }
+    }
+
+    /// 规整单条语句，标识行原样保留；`param_type` 为语句的参数类型
+    fn normalize_stat_sql(&self, origin_sql: &str, param_type: &str) -> String {
+        if is_marker(origin_sql) {
+            String::from(origin_sql)
+        } else {
+            normalizer::normalize(
+                origin_sql,
+                self.dialect_type(),
+                self.template_syntax(),
+                self.normalize_options(),
+                param_type,
+            )
         }
-        sql
     }
 
     fn replace_inc_between_xml(
@@ -107,11 +173,11 @@ pub trait Parser {
         debug!("{sql}");
         let mut new_sql = sql.clone();
         for key in global_inc_map.keys() {
-            let target = format!("{}{}{}", "__INCLUDE_ID_", key, "_END__").to_ascii_uppercase();
+            let target = format!("{}{}{}", "__INCLUDE_ID_", key, "_END__");
             debug!("{target}");
             new_sql = replace_included_sql(
                 &new_sql,
-                key.to_ascii_uppercase().as_str(),
+                key.as_str(),
                 global_inc_map.get(key).unwrap_or(&target).as_str(),
             )
         }
@@ -153,42 +219,47 @@ pub trait Parser {
         global_inc_map: &mut HashMap<String, String>,
     ) {
         let mut sql_parsed = Vec::new();
-        self.read_xml(file, &mut sql_parsed, global_inc_map);
+        let mut reader = fs::File::open(file).unwrap_or_else(|e| {
+            warn!("open file [{file}] failed: {e}");
+            process::exit(-1);
+        });
+        self.read_xml(file, &mut reader, &mut sql_parsed, global_inc_map);
         for sql in sql_parsed {
             sql_store.push(sql);
         }
     }
 
+    /// 从任意输入读取 `xml` 文档，`filename` 仅用于输出标识
     fn read_xml(
         &self,
         filename: &String,
+        reader: &mut dyn Read,
         sql_store: &mut Vec<String>,
         global_inc_map: &mut HashMap<String, String>,
     ) {
-        let mut file_inc_map = HashMap::new();
-        let filename_sql = compose_comment(
-            &comment_leading(self.dialect_type()),
-            &filename.to_string(),
-            &comment_tailing(self.dialect_type()),
-        );
-        let file = fs::File::open(filename).unwrap_or_else(|e| {
-            warn!("open file [{filename}] failed: {e}");
-            process::exit(-1);
-        });
-        let buf = BufReader::new(file);
-        let parser = EventReader::new(buf);
+        let parsed = self.parse_xml(filename, reader);
+        self.fill_parsed(filename, &parsed, sql_store, global_inc_map);
+    }
+
+    /// 解析 `xml` 文档，收集语句与 `<sql>` 片段
+    fn parse_xml(&self, filename: &str, reader: &mut dyn Read) -> ParsedXml {
+        let buf = BufReader::new(reader);
+        let mut parser = EventReader::new(buf);
         let mut state = XmlParsedState::new();
-        state.filename = filename.clone();
-        for e in parser {
+        state.filename = filename.to_string();
+        loop {
+            let e = parser.next();
             match e {
                 Ok(XmlEvent::StartElement {
                     name, attributes, ..
-                }) => self.parse_start_element(name, attributes, &mut state),
-                Ok(XmlEvent::EndElement { name }) => {
-                    self.parse_end_element(name, &mut state, global_inc_map, &mut file_inc_map)
+                }) => {
+                    state.element_line = parser.position().row + 1;
+                    self.parse_start_element(name, attributes, &mut state)
                 }
+                Ok(XmlEvent::EndElement { name }) => self.parse_end_element(name, &mut state),
                 Ok(XmlEvent::CData(content)) => self.fill_xml_content(&mut state, content),
                 Ok(XmlEvent::Characters(content)) => self.fill_xml_content(&mut state, content),
+                Ok(XmlEvent::EndDocument) => break,
                 Err(e) => {
                     warn!("Error: {e}");
                     break;
@@ -196,26 +267,66 @@ pub trait Parser {
                 _ => {}
             }
         }
-        self.replace_and_fill(sql_store, &state.statements, &file_inc_map);
+        ParsedXml {
+            namespace: state.namespace,
+            statements: state.statements,
+            sql_parts: state.sql_parts,
+            sql_part_defs: state.sql_part_defs,
+        }
+    }
+
+    /// 登记 `<sql>` 片段到全局，替换本文件内的 `include` 后输出
+    fn fill_parsed(
+        &self,
+        filename: &String,
+        parsed: &ParsedXml,
+        sql_store: &mut Vec<String>,
+        global_inc_map: &mut HashMap<String, String>,
+    ) {
+        for (id, sql) in parsed.sql_parts.iter() {
+            global_inc_map.insert(format!("{}.{}", parsed.namespace, id), sql.clone());
+        }
+        let filename_sql = compose_comment(
+            &comment_leading(self.dialect_type()),
+            &filename.to_string(),
+            &comment_tailing(self.dialect_type()),
+        );
+        self.replace_and_fill(
+            sql_store,
+            &parsed.namespace,
+            &parsed.statements,
+            &parsed.sql_parts,
+        );
         if !sql_store.is_empty() {
             sql_store.insert(0, filename_sql);
         }
     }
 
     fn fill_xml_content(&self, state: &mut XmlParsedState, content: String) {
+        if state.in_statement && !state.in_sql_key {
+            if let Some(node) = state.dynamic_stack.last_mut() {
+                node.children.push(DynamicPart::Text(content.clone()));
+            }
+        }
         self.fill_content(state, content);
         if state.in_loop {
             self.fill_content(state, state.loop_def.separator.clone());
         }
     }
 
+    /// 追加内容；元素两侧的文本会粘成一个词时（如 `#{a}</if><if>and`）以空格分开
     fn fill_content(&self, state: &mut XmlParsedState, content: String) {
         if state.in_statement {
-            if state.in_sql_key {
-                state.key_sql_builder += content.as_str();
+            let boundary = std::mem::take(&mut state.at_boundary);
+            let builder = if state.in_sql_key {
+                &mut state.key_sql_builder
             } else {
-                state.sql_builder += content.as_str();
+                &mut state.sql_builder
+            };
+            if boundary && is_glued(builder, &content) {
+                builder.push(' ');
             }
+            *builder += content.as_str();
         }
     }
 
@@ -226,6 +337,12 @@ pub trait Parser {
         state: &mut XmlParsedState,
     ) {
         let element_name = name.local_name.as_str().to_ascii_lowercase();
+        state.at_boundary = state.in_statement;
+        if state.in_statement && !state.in_sql_key && element_name != "selectkey" {
+            state
+                .dynamic_stack
+                .push(dynamic_node(&element_name, &attributes));
+        }
         if element_name == "mapper" || element_name == "sqlmap" {
             search_matched_attr(&attributes, "namespace", |attr| {
                 state.namespace = attr.value.clone();
@@ -233,9 +350,17 @@ pub trait Parser {
             });
         } else if match_statement(&element_name) {
             state.in_statement = true;
+            state.current_line = state.element_line;
+            state.dynamic_stack = vec![dynamic_node(&element_name, &attributes)];
             search_matched_attr(&attributes, "id", |attr| {
                 state.current_id = attr.value.clone();
             });
+            search_matched_attr(&attributes, "parameterType", |attr| {
+                state.current_param_type = attr.value.clone();
+            });
+            search_matched_attr(&attributes, "parameterClass", |attr| {
+                state.current_param_type = attr.value.clone();
+            });
         } else if element_name == "selectkey" {
             state.in_sql_key = true;
             state.has_sql_key = true;
@@ -264,18 +389,20 @@ pub trait Parser {
         state: &mut XmlParsedState,
     );
 
-    fn parse_end_element(
-        &self,
-        name: OwnedName,
-        state: &mut XmlParsedState,
-        global_inc_map: &mut HashMap<String, String>,
-        file_inc_map: &mut HashMap<String, String>,
-    ) {
+    fn parse_end_element(&self, name: OwnedName, state: &mut XmlParsedState) {
         let element_name = name.local_name.as_str().to_ascii_lowercase();
+        state.at_boundary = state.in_statement;
+        if state.in_statement && !state.in_sql_key && state.dynamic_stack.len() > 1 {
+            if let Some(node) = state.dynamic_stack.pop() {
+                if let Some(parent) = state.dynamic_stack.last_mut() {
+                    parent.children.push(DynamicPart::Element(node));
+                }
+            }
+        }
         if match_statement(&element_name) {
             let mode = Mode::from(element_name.as_str());
             match mode {
-                Mode::SqlPart => self.handle_end_sql_part(state, global_inc_map, file_inc_map),
+                Mode::SqlPart => self.handle_end_sql_part(state),
                 _ => self.handle_end_statement(mode, state),
             }
         } else if element_name == "selectkey" {
@@ -287,39 +414,71 @@ pub trait Parser {
 
     fn ex_parse_end_element(&self, name: OwnedName, element_name: &str, state: &mut XmlParsedState);
 
-    fn handle_end_sql_part(
-        &self,
-        state: &mut XmlParsedState,
-        global_inc_map: &mut HashMap<String, String>,
-        file_inc_map: &mut HashMap<String, String>,
-    ) {
-        file_inc_map.insert(state.current_id.clone(), state.sql_builder.to_string());
-        global_inc_map.insert(
-            format!("{}.{}", state.namespace, state.current_id.clone()),
-            state.sql_builder.to_string(),
-        );
+    fn handle_end_sql_part(&self, state: &mut XmlParsedState) {
+        state
+            .sql_parts
+            .insert(state.current_id.clone(), state.sql_builder.to_string());
+        state.sql_part_defs.push(SqlPart {
+            id: state.current_id.clone(),
+            sql: state.sql_builder.to_string(),
+            line: state.current_line,
+            dynamic: state.dynamic_stack.pop().unwrap_or_default(),
+        });
         state.reset();
     }
 
     fn handle_end_statement(&self, mode: Mode, state: &mut XmlParsedState) {
-        let sql_stat = SqlStatement::new(
+        let sql_stat = SqlStatement {
             mode,
-            state.current_id.clone(),
-            state.sql_builder.to_string(),
-            state.has_include,
-            state.has_sql_key,
-            SqlKey {
+            id: state.current_id.clone(),
+            sql: state.sql_builder.to_string(),
+            param_type: state.current_param_type.clone(),
+            line: state.current_line,
+            has_include: state.has_include,
+            has_sql_key: state.has_sql_key,
+            sql_key: SqlKey {
                 key: state.current_key_id.clone(),
                 sql: state.key_sql_builder.to_string(),
             },
-        );
+            dynamic: state.dynamic_stack.pop().unwrap_or_default(),
+        };
         state.statements.push(sql_stat);
         state.reset();
     }
 
+    /// 得到单条语句的中间形式：依次替换本文件与跨文件的 `include`，再做规整，占位符尚未替换
+    fn resolve_template(
+        &self,
+        stat: &SqlStatement,
+        file_inc_map: &HashMap<String, String>,
+        global_inc_map: &HashMap<String, String>,
+    ) -> String {
+        let sql = if stat.has_include {
+            loop_replace_include_part(stat, file_inc_map, self.replace_num())
+        } else {
+            stat.sql.clone()
+        };
+        let sql = self.normalize_stat_sql(&sql, &stat.param_type);
+        let sql = self.replace_inc_between_xml(&sql, global_inc_map);
+        // 跨文件的片段是原文，再规整一次统一为中间形式
+        self.normalize_stat_sql(&sql, &stat.param_type)
+    }
+
+    /// 得到取键语句的中间形式
+    fn resolve_key_template(
+        &self,
+        stat: &SqlStatement,
+        global_inc_map: &HashMap<String, String>,
+    ) -> String {
+        let sql = self.normalize_stat_sql(&stat.sql_key.sql, &stat.param_type);
+        let sql = self.replace_inc_between_xml(&sql, global_inc_map);
+        self.normalize_stat_sql(&sql, &stat.param_type)
+    }
+
     fn replace_and_fill(
         &self,
         sql_store: &mut Vec<String>,
+        namespace: &str,
         statements: &Vec<SqlStatement>,
         file_inc_map: &HashMap<String, String>,
     ) {
@@ -329,6 +488,7 @@ pub trait Parser {
             self.replace_single_statement(
                 sql_store,
                 &comment_leading,
+                namespace,
                 stat,
                 &comment_tailing,
                 file_inc_map,
@@ -340,14 +500,16 @@ pub trait Parser {
         &self,
         sql_store: &mut Vec<String>,
         comment_leading: &String,
+        namespace: &str,
         stat: &SqlStatement,
         comment_tailing: &String,
         file_inc_map: &HashMap<String, String>,
     ) {
         debug!("----------------------------------------------------------------");
+        let full_id = full_stat_id(namespace, &stat.id);
         let stat_id_sql = compose_comment(
             &comment_leading.to_string(),
-            &String::from(&stat.id),
+            &self.stat_label(&stat.id, &full_id),
             &comment_tailing.to_string(),
         );
         if stat.has_include {
@@ -355,22 +517,31 @@ pub trait Parser {
                 sql_store,
                 &stat_id_sql,
                 &loop_replace_include_part(stat, file_inc_map, self.replace_num()),
-                self.is_gen_explain(),
+                &stat.param_type,
+                self.explain_prefix(&full_id),
             );
         } else {
-            self.clear_and_push(sql_store, &stat_id_sql, &stat.sql, self.is_gen_explain());
+            self.clear_and_push(
+                sql_store,
+                &stat_id_sql,
+                &stat.sql,
+                &stat.param_type,
+                self.explain_prefix(&full_id),
+            );
         }
         if stat.has_sql_key {
+            let full_key_id = full_stat_id(namespace, &stat.sql_key.key);
             let stat_id_key_sql = compose_comment(
                 &comment_leading.to_string(),
-                &stat.sql_key.key,
+                &self.stat_label(&stat.sql_key.key, &full_key_id),
                 &comment_tailing.to_string(),
             );
             self.clear_and_push(
                 sql_store,
                 &stat_id_key_sql,
                 &stat.sql_key.sql,
-                self.is_gen_explain(),
+                &stat.param_type,
+                self.explain_prefix(&full_key_id),
             );
         }
     }
@@ -380,9 +551,10 @@ pub trait Parser {
         sql_store: &mut Vec<String>,
         id_sql: &str,
         origin_sql: &str,
-        gen_explain: bool,
+        param_type: &str,
+        explain: Option<String>,
     ) {
-        self.loop_clear_and_push(sql_store, id_sql, origin_sql, gen_explain, true);
+        self.loop_clear_and_push(sql_store, id_sql, origin_sql, param_type, explain, true);
     }
 
     fn loop_clear_and_push(
@@ -390,21 +562,57 @@ pub trait Parser {
         sql_store: &mut Vec<String>,
         id_sql: &str,
         origin_sql: &str,
-        gen_explain: bool,
+        param_type: &str,
+        explain: Option<String>,
         append_semicolon: bool,
     ) {
-        let sql = self.replace_sql_by_regex(origin_sql);
-        if gen_explain && append_semicolon {
-            let sql = format!("{}{}{}", explain_dialect(self.dialect_type()), sql, ";");
-            self.push_to_sql_store(sql_store, id_sql, sql, true);
-        } else if !gen_explain && append_semicolon {
-            let sql = sql + ";";
-            self.push_to_sql_store(sql_store, id_sql, sql, false);
-        } else if !append_semicolon && gen_explain {
-            let sql = format!("{}{}", explain_dialect(self.dialect_type()), sql);
-            self.push_to_sql_store(sql_store, id_sql, sql, true);
+        let sql = self.normalize_stat_sql(origin_sql, param_type);
+        let gen_explain = explain.is_some();
+        let sql = format!("{}{}", explain.unwrap_or_default(), sql);
+        if append_semicolon {
+            self.push_to_sql_store(sql_store, id_sql, sql + ";", gen_explain);
         } else {
-            self.push_to_sql_store(sql_store, id_sql, sql, false);
+            self.push_to_sql_store(sql_store, id_sql, sql, gen_explain);
+        }
+    }
+
+    /// 执行计划前缀，未开启时为 `None`；`full_id` 为 `命名空间.id`
+    fn explain_prefix(&self, full_id: &str) -> Option<String> {
+        if self.is_gen_explain() {
+            Some(explain_dialect(
+                self.dialect_type(),
+                self.explain_format(),
+                full_id,
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// 语句标识行的内容，`STATEMENT_ID` 为摘要时注明其对应的 `命名空间.id`
+    fn stat_label(&self, stat_id: &str, full_id: &str) -> String {
+        if self.is_gen_explain()
+            && matches!(
+                (self.dialect_type(), self.explain_format()),
+                (DialectType::Oracle, ExplainFormat::PlanTable)
+            )
+        {
+            format!(
+                "{stat_id}, STATEMENT_ID {} = {full_id}",
+                statement_id(full_id)
+            )
+        } else {
+            String::from(stat_id)
+        }
+    }
+
+    /// 全部语句之后的执行计划查询，`Oracle` 的 `plan-table` 格式统一查询 `PLAN_TABLE`
+    fn explain_epilogue(&self) -> Vec<String> {
+        match (self.dialect_type(), self.explain_format()) {
+            (DialectType::Oracle, ExplainFormat::PlanTable) if self.is_gen_explain() => {
+                vec![String::from(PLAN_TABLE_QUERY)]
+            }
+            _ => Vec::new(),
         }
     }
 
@@ -415,7 +623,9 @@ pub trait Parser {
         sql: String,
         append_oracle_list_plan: bool,
     ) {
-        if !self.is_sql_limit() || (self.is_sql_limit() && sql.len() > self.sql_limit() as usize) {
+        // 暂存的是中间形式，按替换占位符后的长度过滤
+        if !self.is_sql_limit() || self.bind_stat_sql(&sql, "").1.len() > self.sql_limit() as usize
+        {
             sql_store.push(String::from(id_sql));
             sql_store.push(sql);
             if append_oracle_list_plan {
@@ -424,26 +634,28 @@ pub trait Parser {
         }
     }
 
-    fn regex_clear_and_push(
-        &self,
-        origin_sql: &str,
-        regex_replacement: &RegexReplacement,
-    ) -> String {
-        regex_replacement
-            .regex
-            .replace_all(origin_sql, regex_replacement.target.as_str())
-            .to_string()
-    }
-
-    fn vec_regex(&self) -> &Vec<RegexReplacement>;
-
     fn append_oracle_list_plan(&self, sql_store: &mut Vec<String>) {
-        if let DialectType::Oracle = self.dialect_type() {
+        if let (DialectType::Oracle, ExplainFormat::Text) =
+            (self.dialect_type(), self.explain_format())
+        {
             sql_store.push(String::from("SELECT * FROM TABLE(DBMS_XPLAN.DISPLAY);"))
         }
     }
 }
 
+/// 前文结尾与后文开头直接相连时是否会被当作同一个词或占位符
+fn is_glued(before: &str, after: &str) -> bool {
+    let end = before.chars().next_back();
+    let start = after.chars().next();
+    end.is_some_and(|c| c.is_alphanumeric() || "_}#$'\"`".contains(c))
+        && start.is_some_and(|c| c.is_alphanumeric() || "_#$'\"`:@?".contains(c))
+}
+
+/// 是否为文件、语句标识行或执行计划查询行
+fn is_marker(sql: &str) -> bool {
+    XML_REGEX.is_match(sql) || STAT_REGEX.is_match(sql) || ORA_QUERY_PLAN_REGEX.is_match(sql)
+}
+
 fn loop_replace_include_part(
     stat: &SqlStatement,
     file_inc_map: &HashMap<String, String>,
@@ -490,6 +702,7 @@ fn comment_leading(dialet_type: &DialectType) -> String {
     match dialet_type {
         DialectType::Oracle => "SELECT \"XML-FILE: ".to_string(),
         DialectType::MySQL => "SELECT \"XML-FILE: ".to_string(),
+        _ => "SELECT 'XML-FILE: ".to_string(),
     }
 }
 
@@ -497,6 +710,7 @@ fn comment_leading2(dialet_type: &DialectType) -> String {
     match dialet_type {
         DialectType::Oracle => "SELECT \"STAT-ID: ".to_string(),
         DialectType::MySQL => "SELECT \"STAT-ID: ".to_string(),
+        _ => "SELECT 'STAT-ID: ".to_string(),
     }
 }
 
@@ -504,6 +718,9 @@ fn comment_tailing(dialet_type: &DialectType) -> String {
     match dialet_type {
         DialectType::Oracle => "\" AS XML_FILE FROM DUAL;".to_string(),
         DialectType::MySQL => "\" AS XML_FILE;".to_string(),
+        DialectType::Dm | DialectType::OceanBaseOracle => "' AS XML_FILE FROM DUAL;".to_string(),
+        DialectType::Db2 => "' AS XML_FILE FROM SYSIBM.SYSDUMMY1;".to_string(),
+        _ => "' AS XML_FILE;".to_string(),
     }
 }
 
@@ -511,6 +728,20 @@ fn comment_tailing2(dialet_type: &DialectType) -> String {
     match dialet_type {
         DialectType::Oracle => "\" AS STAT_ID FROM DUAL;".to_string(),
         DialectType::MySQL => "\" AS STAT_ID;".to_string(),
+        DialectType::Dm | DialectType::OceanBaseOracle => "' AS STAT_ID FROM DUAL;".to_string(),
+        DialectType::Db2 => "' AS STAT_ID FROM SYSIBM.SYSDUMMY1;".to_string(),
+        _ => "' AS STAT_ID;".to_string(),
+    }
+}
+
+fn dynamic_node(tag: &str, attributes: &[OwnedAttribute]) -> DynamicNode {
+    DynamicNode {
+        tag: tag.to_string(),
+        attrs: attributes
+            .iter()
+            .map(|a| (a.name.local_name.clone(), a.value.clone()))
+            .collect(),
+        children: Vec::new(),
     }
 }
 
@@ -520,14 +751,301 @@ fn compose_comment(leading: &String, line: &String, trailing: &String) -> String
 
 pub(crate) fn var_placeholder(dialect_type: &DialectType) -> &str {
     match dialect_type {
-        DialectType::Oracle => ":?",
-        DialectType::MySQL => "@1",
+        DialectType::Oracle | DialectType::Dm | DialectType::OceanBaseOracle => ":?",
+        DialectType::MySQL | DialectType::OceanBaseMySql => "@1",
+        // `DB2` 的参数标记 `?` 可以直接交给 `CLP` 执行 `EXPLAIN`
+        DialectType::Sqlite
+        | DialectType::H2
+        | DialectType::Kingbase
+        | DialectType::OpenGauss
+        | DialectType::Db2 => "?",
     }
 }
 
-fn explain_dialect(dialect_type: &DialectType) -> &str {
-    match dialect_type {
-        DialectType::Oracle => "explain plan for ",
-        DialectType::MySQL => "explain ",
+fn explain_dialect(dialect_type: &DialectType, format: ExplainFormat, full_id: &str) -> String {
+    match (dialect_type, format) {
+        (DialectType::Oracle, ExplainFormat::PlanTable) => format!(
+            "explain plan set STATEMENT_ID = '{}' for ",
+            statement_id(full_id)
+        ),
+        (DialectType::Oracle, _) => String::from("explain plan for "),
+        (DialectType::MySQL, ExplainFormat::Json) => String::from("explain format=JSON "),
+        (DialectType::MySQL, ExplainFormat::Analyze) => String::from("explain analyze "),
+        (DialectType::MySQL, _) => String::from("explain "),
+        (DialectType::Sqlite, _) => String::from("explain query plan "),
+        (DialectType::H2, ExplainFormat::Analyze) => String::from("explain analyze "),
+        (DialectType::H2, _) => String::from("explain "),
+        (DialectType::Db2, _) => format!("explain plan set QUERYNO = {QUERY_NO_PLACEHOLDER} for "),
+        (DialectType::Kingbase | DialectType::OpenGauss, ExplainFormat::Json) => {
+            String::from("explain (format json) ")
+        }
+        (DialectType::Kingbase | DialectType::OpenGauss, ExplainFormat::Analyze) => {
+            String::from("explain analyze ")
+        }
+        (DialectType::OceanBaseMySql, ExplainFormat::Json) => String::from("explain format=JSON "),
+        (
+            DialectType::Dm
+            | DialectType::Kingbase
+            | DialectType::OpenGauss
+            | DialectType::OceanBaseOracle
+            | DialectType::OceanBaseMySql,
+            _,
+        ) => String::from("explain "),
+    }
+}
+
+/// 语句的完整标识 `命名空间.id`
+pub fn full_stat_id(namespace: &str, stat_id: &str) -> String {
+    if namespace.is_empty() {
+        String::from(stat_id)
+    } else {
+        format!("{namespace}.{stat_id}")
+    }
+}
+
+/// `PLAN_TABLE.STATEMENT_ID` 最长 30 个字符，`命名空间.id` 常常超出，取其摘要的前 16 位
+fn statement_id(full_id: &str) -> String {
+    Sha256::digest(full_id.as_bytes())
+        .iter()
+        .take(8)
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::xbatis::{ibatis_parser, mybatis_parser};
+
+    /// 解析单个映射文件，依次得到各语句最终的 `sql`
+    fn resolve_all(parser: &dyn Parser, xml: &str) -> Vec<String> {
+        let parsed = parser.parse_xml("test.xml", &mut xml.as_bytes());
+        parsed
+            .statements
+            .iter()
+            .map(|stat| {
+                let template = parser.resolve_template(stat, &parsed.sql_parts, &HashMap::new());
+                parser.bind_stat_sql(&template, &stat.param_type).1
+            })
+            .collect()
+    }
+
+    /// 按输出顺序得到单个映射文件的全部输出行
+    fn output_all(parser: &dyn Parser, xml: &str) -> Vec<String> {
+        let mut sql_store = Vec::new();
+        let mut global_inc_map = HashMap::new();
+        parser.read_xml(
+            &String::from("test.xml"),
+            &mut xml.as_bytes(),
+            &mut sql_store,
+            &mut global_inc_map,
+        );
+        let mut query_no = 0;
+        sql_store
+            .iter()
+            .map(|sql| parser.finalize_sql(sql, &global_inc_map, &mut query_no))
+            .collect()
+    }
+
+    #[test]
+    fn sqlite_and_h2_markers_and_explain() {
+        let xml = r#"<mapper namespace="m"><select id="a">SELECT a FROM t WHERE id = #{id}</select></mapper>"#;
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::Sqlite);
+        parser.setup_gen_explain(true);
+        assert_eq!(
+            output_all(&parser, xml),
+            [
+                "SELECT 'XML-FILE: test.xml' AS XML_FILE;",
+                "SELECT 'STAT-ID: a' AS STAT_ID;",
+                "EXPLAIN QUERY PLAN SELECT a FROM t WHERE id = ?;"
+            ]
+        );
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::H2);
+        parser.setup_gen_explain(true);
+        parser.setup_explain_format(ExplainFormat::Analyze);
+        assert_eq!(
+            output_all(&parser, xml),
+            [
+                "SELECT 'XML-FILE: test.xml' AS XML_FILE;",
+                "SELECT 'STAT-ID: a' AS STAT_ID;",
+                "EXPLAIN ANALYZE SELECT a FROM t WHERE id = ?;"
+            ]
+        );
+    }
+
+    #[test]
+    fn domestic_and_oceanbase_markers_and_explain() {
+        let xml = r#"<mapper namespace="m"><select id="a">SELECT a FROM t WHERE id = #{id}</select></mapper>"#;
+        let cases = [
+            (
+                DialectType::Dm,
+                ExplainFormat::Text,
+                " FROM DUAL;",
+                "EXPLAIN SELECT a FROM t WHERE id = :?;",
+            ),
+            (
+                DialectType::Kingbase,
+                ExplainFormat::Json,
+                ";",
+                "EXPLAIN (FORMAT json) SELECT a FROM t WHERE id = ?;",
+            ),
+            (
+                DialectType::OpenGauss,
+                ExplainFormat::Analyze,
+                ";",
+                "EXPLAIN ANALYZE SELECT a FROM t WHERE id = ?;",
+            ),
+            (
+                DialectType::OceanBaseOracle,
+                ExplainFormat::Text,
+                " FROM DUAL;",
+                "EXPLAIN SELECT a FROM t WHERE id = :?;",
+            ),
+            (
+                DialectType::OceanBaseMySql,
+                ExplainFormat::Json,
+                ";",
+                "EXPLAIN FORMAT=JSON SELECT a FROM t WHERE id = @1;",
+            ),
+        ];
+        for (dialect, format, tailing, explain) in cases {
+            let mut parser = mybatis_parser::create_mybatis_parser(dialect);
+            parser.setup_gen_explain(true);
+            parser.setup_explain_format(format);
+            assert_eq!(
+                output_all(&parser, xml),
+                [
+                    format!("SELECT 'XML-FILE: test.xml' AS XML_FILE{tailing}"),
+                    format!("SELECT 'STAT-ID: a' AS STAT_ID{tailing}"),
+                    String::from(explain)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn include_ids_ignore_case() {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::Dm);
+        parser.setup_replace_num(10);
+        let output = output_all(
+            &parser,
+            r#"<mapper namespace="m">
+            <sql id="Cond">status = 'A'</sql>
+            <select id="a">SELECT * FROM t WHERE <include refid="COND"/></select>
+            <select id="b">SELECT * FROM t WHERE <include refid="m.cond"/></select>
+            </mapper>"#,
+        );
+        assert_eq!(output[2], "SELECT * FROM t WHERE status = 'A';");
+        assert_eq!(output[4], "SELECT * FROM t WHERE status = 'A';");
+    }
+
+    #[test]
+    fn reads_from_any_reader() {
+        let parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        let mut sql_store = Vec::new();
+        parser.read_xml(
+            &String::from("<stdin>"),
+            &mut r#"<mapper namespace="m"><select id="a">SELECT 1</select></mapper>"#.as_bytes(),
+            &mut sql_store,
+            &mut HashMap::new(),
+        );
+        assert_eq!(sql_store.len(), 3);
+        assert_eq!(sql_store[0], "SELECT \"XML-FILE: <stdin>\" AS XML_FILE;");
+        assert_eq!(sql_store[1], "SELECT \"STAT-ID: a\" AS STAT_ID;");
+        assert!(sql_store[2].starts_with("SELECT 1"));
+    }
+
+    #[test]
+    fn element_boundaries_do_not_glue_text() {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        parser.setup_replace_num(10);
+        let sqls = resolve_all(
+            &parser,
+            r#"<mapper namespace="m">
+            <delete id="a">DELETE FROM t WHERE 1=1 <if test="a">AND a = #{a}</if><if test="b">AND b = #{b}</if></delete>
+            <delete id="b">DELETE FROM t WHERE <choose><when test="id">id = #{id}</when><otherwise>status = 'X'</otherwise></choose></delete>
+            <select id="c">SELECT * FROM t WHERE <include refid="cond"/>AND x = 1</select>
+            <sql id="cond">y = 2</sql>
+            </mapper>"#,
+        );
+        assert_eq!(sqls[0], "DELETE FROM t WHERE 1=1 AND a = @1 AND b = @1");
+        assert_eq!(sqls[1], "DELETE FROM t WHERE id = @1 status = 'X'");
+        assert_eq!(sqls[2], "SELECT * FROM t WHERE y = 2 AND x = 1");
+    }
+
+    #[test]
+    fn element_boundaries_keep_punctuation_tight() {
+        let parser = mybatis_parser::create_mybatis_parser(DialectType::MySQL);
+        let sqls = resolve_all(
+            &parser,
+            r#"<mapper namespace="m">
+            <select id="a">SELECT * FROM t WHERE id IN <foreach collection="ids" item="x" open="(" close=")" separator=",">#{x}</foreach> AND (<if test="a">a = 1</if>)</select>
+            </mapper>"#,
+        );
+        assert_eq!(sqls[0], "SELECT * FROM t WHERE id IN (@1) AND (a = 1)");
+    }
+
+    #[test]
+    fn ibatis_boundaries_do_not_glue_text() {
+        let parser = ibatis_parser::create_ibatis_parser(DialectType::Oracle);
+        let sqls = resolve_all(
+            &parser,
+            r#"<sqlMap namespace="s">
+            <select id="a">SELECT * FROM t WHERE 1=1<isNotNull property="a">AND a = #a#</isNotNull><isNotNull property="b">AND b = #b#</isNotNull></select>
+            </sqlMap>"#,
+        );
+        assert_eq!(sqls[0], "SELECT * FROM t WHERE 1=1 AND a = :? AND b = :?");
+    }
+
+    #[test]
+    fn query_numbers_follow_output_order_and_restart_per_run() {
+        let mut parser = mybatis_parser::create_mybatis_parser(DialectType::Db2);
+        parser.setup_gen_explain(true);
+        let parsed = parser.parse_xml(
+            "test.xml",
+            &mut r#"<mapper namespace="m">
+            <select id="a">SELECT a FROM t</select>
+            <select id="b">SELECT b FROM t</select>
+            </mapper>"#
+                .as_bytes(),
+        );
+        let mut global_inc_map = HashMap::new();
+        let mut sql_store = Vec::new();
+        parser.fill_parsed(
+            &String::from("test.xml"),
+            &parsed,
+            &mut sql_store,
+            &mut global_inc_map,
+        );
+        for _ in 0..2 {
+            let mut query_no = 0;
+            let explains: Vec<String> = sql_store
+                .iter()
+                .map(|sql| parser.finalize_sql(sql, &global_inc_map, &mut query_no))
+                .filter(|sql| sql.starts_with("EXPLAIN"))
+                .collect();
+            assert_eq!(
+                explains,
+                [
+                    "EXPLAIN PLAN SET QUERYNO = 1 FOR SELECT a FROM t;",
+                    "EXPLAIN PLAN SET QUERYNO = 2 FOR SELECT b FROM t;"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn statement_ids_are_distinct_and_short() {
+        let long = "selectOrdersWithCustomerAndItemsByDate";
+        let ids = [
+            statement_id(&full_stat_id("a", "select")),
+            statement_id(&full_stat_id("b", "select")),
+            statement_id(&full_stat_id("a", &format!("{long}Range"))),
+            statement_id(&full_stat_id("a", &format!("{long}Page"))),
+        ];
+        for (i, id) in ids.iter().enumerate() {
+            assert!(id.len() <= 30);
+            assert!(ids[i + 1..].iter().all(|other| other != id));
+        }
     }
 }